@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error,
+    config::Config,
+    storage::{self, Storage},
+};
+
+/// providers which need an api key.
+pub const PROVIDERS: [&str; 4] = ["anthropic", "deepseek", "gemini", "openai"];
+
+/// api keys stored by `ggw auth set`. provider -> key.
+/// no `Debug` not to print keys.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Credentials {
+    keys: HashMap<String, String>,
+}
+
+impl<P: AsRef<Path>> Storage<P> for Credentials {}
+
+/// kept apart from config, which may be shared.
+pub fn credentials_path() -> Result<PathBuf, Error> {
+    Ok(home::home_dir()
+        .ok_or(Error::NotFoundHome)?
+        .join(".ggw")
+        .join("credentials.json"))
+}
+
+impl Credentials {
+    /// empty when the file doesn't exist.
+    pub fn load() -> Result<Self, Error> {
+        let path = credentials_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path)
+                .map_err(Error::IoE)?
+                .permissions()
+                .mode();
+            if mode & 0o077 != 0 {
+                eprintln!(
+                    "warning: {} is accessible by other users. run `chmod 600` on it.",
+                    path.to_string_lossy()
+                );
+            }
+        }
+        Self::open::<Self>(path).map_err(Error::StrE)
+    }
+
+    /// written with mode 0600.
+    pub fn store(&self) -> Result<PathBuf, Error> {
+        let path = credentials_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::IoE)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // mode of open() applies only to a new file
+            if path.exists() {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                    .map_err(Error::IoE)?;
+            }
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::StrE(storage::Error::Serde(e)))?;
+        options
+            .open(&path)
+            .and_then(|mut f| f.write_all(json.as_bytes()))
+            .map_err(Error::IoE)?;
+        Ok(path)
+    }
+
+    pub fn get(&self, provider: &str) -> Option<&String> {
+        self.keys.get(provider)
+    }
+
+    pub fn set<T: AsRef<str>>(&mut self, provider: &str, key: T) {
+        self.keys
+            .insert(provider.to_string(), key.as_ref().trim().to_string());
+    }
+
+    /// `false` when not stored.
+    pub fn remove(&mut self, provider: &str) -> bool {
+        self.keys.remove(provider).is_some()
+    }
+}
+
+pub fn env_name(provider: &str) -> Option<&'static str> {
+    match provider {
+        "anthropic" => Some("GGW_ANTHROPIC_API"),
+        "deepseek" => Some("GGW_DEEPSEEK_API"),
+        "gemini" => Some("GGW_GEMINI_API"),
+        "openai" => Some("GGW_OPENAI_API"),
+        _ => None,
+    }
+}
+
+/// first line of stdout of `command`, run by `sh -c`. e.g. `pass show gemini`
+pub fn key_from_command<T: AsRef<str>>(command: T) -> Result<String, Error> {
+    let command = command.as_ref();
+    let out = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(Error::IoE)?;
+    // the output is never shown. it may be the key.
+    if !out.status.success() {
+        return Err(Error::KeyCommand(format!("`{command}` {}", out.status)));
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .ok_or(Error::KeyCommand(format!("`{command}` printed nothing")))
+}
+
+/// where the key of `provider` comes from, in order of precedence.
+pub enum KeySource {
+    Env(&'static str),
+    Command(String),
+    Stored,
+}
+
+impl Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySource::Env(name) => write!(f, "env {name}"),
+            KeySource::Command(cmd) => write!(f, "key_command `{cmd}`"),
+            KeySource::Stored => write!(f, "stored"),
+        }
+    }
+}
+
+pub fn key_source(provider: &str, config: &Config, creds: &Credentials) -> Option<KeySource> {
+    if let Some(name) = env_name(provider).filter(|n| env::var(n).is_ok_and(|v| !v.is_empty())) {
+        return Some(KeySource::Env(name));
+    }
+    if let Some(cmd) = config.key_command().as_ref().and_then(|c| c.get(provider)) {
+        return Some(KeySource::Command(cmd.clone()));
+    }
+    creds.get(provider).map(|_| KeySource::Stored)
+}
+
+/// api key of a cloud provider. env > `key_command` in config > `ggw auth set`.
+/// `None` for ollama and unknown providers.
+pub fn resolve_key(provider: &str, config: &Config) -> Result<Option<String>, Error> {
+    if env_name(provider).is_none() {
+        return Ok(None);
+    }
+    let creds = Credentials::load()?;
+    match key_source(provider, config, &creds) {
+        Some(KeySource::Env(name)) => Ok(env::var(name).ok()),
+        Some(KeySource::Command(cmd)) => key_from_command(cmd).map(Some),
+        Some(KeySource::Stored) => Ok(creds.get(provider).cloned()),
+        None => Err(Error::NotFoundApiKey(provider.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::key_from_command;
+
+    #[test]
+    fn test_key_from_command() {
+        assert_eq!(
+            key_from_command("printf ' abc \\nsecond\\n'").unwrap(),
+            "abc"
+        );
+        let e = key_from_command("echo $((6 * 7)); exit 3").unwrap_err();
+        assert!(!e.to_string().contains("42"));
+        assert!(key_from_command("true").is_err());
+    }
+}
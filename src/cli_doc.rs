@@ -0,0 +1,205 @@
+use clap::{Arg, Command, ValueEnum};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DocFormat {
+    Markdown,
+    Man,
+}
+
+/// `-m`, `--model <MODEL>` or `<PROVIDER>`
+fn arg_name(arg: &Arg) -> String {
+    let value = arg
+        .get_value_names()
+        .map(|v| {
+            v.iter()
+                .map(|n| format!("<{n}>"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_else(|| format!("<{}>", arg.get_id().as_str().to_uppercase()));
+    if arg.is_positional() {
+        return value;
+    }
+    let mut names = Vec::new();
+    if let Some(s) = arg.get_short() {
+        names.push(format!("-{s}"));
+    }
+    if let Some(l) = arg.get_long() {
+        names.push(format!("--{l}"));
+    }
+    let mut name = names.join(", ");
+    if arg.get_action().takes_values() {
+        name = format!("{name} {value}");
+    }
+    name
+}
+
+/// help with default, possible values and flags.
+fn arg_help(arg: &Arg) -> String {
+    let mut help = arg.get_help().map(|h| h.to_string()).unwrap_or_default();
+    let mut notes = Vec::new();
+    if arg.is_required_set() {
+        notes.push("required".to_string());
+    }
+    if arg.is_global_set() {
+        notes.push("global".to_string());
+    }
+    let defaults = arg
+        .get_default_values()
+        .iter()
+        .map(|d| d.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    // flags have "false" as default
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        notes.push(format!("default: {}", defaults.join(", ")));
+    }
+    let possible = arg
+        .get_possible_values()
+        .iter()
+        .filter(|p| !p.is_hide_set())
+        .map(|p| p.get_name().to_string())
+        .collect::<Vec<_>>();
+    if !possible.is_empty() && arg.get_action().takes_values() {
+        notes.push(format!("values: {}", possible.join(", ")));
+    }
+    if !notes.is_empty() {
+        help = format!("{help} ({})", notes.join("; ")).trim().to_string();
+    }
+    help
+}
+
+fn visible_args(cmd: &Command) -> impl Iterator<Item = &Arg> {
+    cmd.get_arguments().filter(|a| !a.is_hide_set())
+}
+
+/// `path` is the names from the root. e.g. `ggw auth`
+fn walk<'a>(cmd: &'a Command, path: String, out: &mut Vec<(String, &'a Command)>) {
+    out.push((path.clone(), cmd));
+    for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+        walk(sub, format!("{path} {}", sub.get_name()), out);
+    }
+}
+
+fn commands(cmd: &Command) -> Vec<(String, &Command)> {
+    let mut out = Vec::new();
+    walk(cmd, cmd.get_name().to_string(), &mut out);
+    out
+}
+
+/// a section and an option table per command. same input, same output.
+pub fn markdown(cmd: &Command) -> String {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    // `<name>` outside code spans would be taken as html
+    let text = |s: &str| {
+        s.split('`')
+            .enumerate()
+            .map(|(i, part)| {
+                if i % 2 == 0 {
+                    part.replace('<', "&lt;")
+                } else {
+                    part.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("`")
+    };
+    let mut doc = Vec::new();
+    for (path, c) in commands(cmd) {
+        let mut s = format!("#### `{path}`\n");
+        if let Some(about) = c.get_about() {
+            s.push_str(&format!("\n{about}\n"));
+        }
+        let subs = c
+            .get_subcommands()
+            .filter(|s| !s.is_hide_set())
+            .map(|s| s.get_name())
+            .collect::<Vec<_>>();
+        if !subs.is_empty() {
+            s.push_str(&format!(
+                "\nsubcommands: {}\n",
+                subs.iter()
+                    .map(|n| format!("`{n}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let rows = visible_args(c)
+            .map(|a| {
+                format!(
+                    "| `{}` | {} |",
+                    cell(&arg_name(a)),
+                    cell(&text(&arg_help(a)))
+                )
+            })
+            .collect::<Vec<_>>();
+        if !rows.is_empty() {
+            s.push_str(&format!(
+                "\n| option | description |\n| :--- | :--- |\n{}\n",
+                rows.join("\n")
+            ));
+        }
+        doc.push(s);
+    }
+    doc.join("\n")
+}
+
+/// roff for `man`.
+pub fn man(cmd: &Command) -> String {
+    let esc = |s: &str| s.replace('\\', "\\\\").replace('-', "\\-");
+    let name = cmd.get_name();
+    let about = cmd.get_about().map(|a| a.to_string()).unwrap_or_default();
+    let version = cmd.get_version().unwrap_or_default();
+    let mut roff = format!(
+        ".TH {} 1 \"\" \"{name} {version}\"\n.SH NAME\n{name} \\- {}\n",
+        name.to_uppercase(),
+        esc(&about)
+    );
+    let options = |c: &Command| {
+        visible_args(c)
+            .map(|a| {
+                format!(
+                    ".TP\n\\fB{}\\fR\n{}\n",
+                    esc(&arg_name(a)),
+                    esc(&arg_help(a))
+                )
+            })
+            .collect::<String>()
+    };
+    roff.push_str(&format!(".SH OPTIONS\n{}", options(cmd)));
+    roff.push_str(".SH COMMANDS\n");
+    for (path, c) in commands(cmd).into_iter().skip(1) {
+        roff.push_str(&format!(".SS \"{}\"\n", esc(&path)));
+        if let Some(about) = c.get_about() {
+            roff.push_str(&format!("{}\n", esc(&about.to_string())));
+        }
+        roff.push_str(&options(c));
+    }
+    roff
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::{man, markdown};
+    use crate::Cli;
+
+    #[test]
+    fn test_render() {
+        let cmd = Cli::command();
+        let md = markdown(&cmd);
+        assert!(md.starts_with("#### `ggw`\n"));
+        assert!(md.contains("| `-m, --model <MODEL>` | -m gemini/gemini-2.0-flash |"));
+        assert!(md.contains("#### `ggw auth set`"));
+        assert!(md.contains(
+            "| `<PROVIDER>` | (required; values: anthropic, deepseek, gemini, openai) |"
+        ));
+        assert!(md.contains("--author \"Name &lt;email>\""));
+        assert_eq!(md, markdown(&cmd));
+
+        let roff = man(&cmd);
+        assert!(roff.starts_with(".TH GGW 1"));
+        assert!(roff.contains(".SS \"ggw cmt\""));
+        assert!(roff.contains("\\fB\\-y, \\-\\-yes\\fR"));
+    }
+}
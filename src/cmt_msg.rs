@@ -1,26 +1,37 @@
-use crate::{Error, Model, llm};
+use crate::{
+    Error, Model, guard, llm,
+    template::{Task, Template},
+};
 
-const GEN_MSG_PMT: &str = "You are an assistant that writes Git commit messages.\
+pub const GEN_MSG_PMT: &str = "You are an assistant that writes Git commit messages.\
 When code changes include modifications to documentation files (e.g., README.md, docs/), ignore those changes and generate the commit message based solely on source code changes.\
 Given a description of code changes, output only a single-line commit message in Conventional Commits format (e.g., \"feat:\", \"fix:\", \"docs:\", etc.).\
-Do not include any extra text, code blocks, or formatting. Only output the commit message.\
-Changes:\n";
+Do not include any extra text, code blocks, or formatting. Only output the commit message.\n\
+{{hints}}{{user}}Changes:\n{{diff}}\n\
+Write the commit message for the changes above, and only the commit message.";
 
+/// `template` has other variables. (e.g. hints, branch)
 pub fn create_cmt_msg<T: AsRef<str>>(
     diff: T,
+    template: Template,
     model: Model,
     api_key: Option<T>,
 ) -> Result<String, Error> {
-    let pmt = format!("{GEN_MSG_PMT} {}", diff.as_ref());
-    llm::call_llm(
-        pmt.to_string(),
+    let conventional = template.is_default(Task::Cmt);
+    let (system, pmt) = template
+        .var("diff", guard::fence("diff", &diff))
+        .render_parts();
+    let res = llm::call_llm(
+        system,
+        pmt,
         model.provider,
         model.model,
         api_key.map(|f| f.as_ref().to_string()),
         None,
         None,
     )
-    .map_err(Error::Llm)
+    .map_err(Error::Llm)?;
+    guard::check_cmt_msg(res, diff, conventional)
 }
 
 #[cfg(test)]
@@ -29,6 +40,7 @@ mod tests {
 
     use crate::Model;
     use crate::cmt_msg::create_cmt_msg;
+    use crate::template::{Task, Template};
 
     #[test]
     fn test_cmt_msg() {
@@ -39,6 +51,7 @@ mod tests {
         println!("start");
         let res = create_cmt_msg(
             diff,
+            Template::new(Task::Cmt),
             Model::new("gemini", "gemini-2.0-flash", None, None),
             Some(env::var("GEMINI_API_KEY").unwrap()),
         );
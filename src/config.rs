@@ -1,18 +1,100 @@
 use crate::{Cli, Error, storage::Storage};
+use derive_getters::Getters;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::Path};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default, Getters)]
 pub struct Config {
+    #[getter(skip)]
     prompt: Option<Prompt>,
+    #[getter(skip)]
     llm: Option<Llm>,
+    /// members for `--co-author`. alias -> "Name <email>"
+    team: Option<HashMap<String, String>>,
+    /// extract ticket ids from branch name. default rules are used when not set.
+    issue_rules: Option<Vec<IssueRule>>,
+    /// learn commit style from history.
+    history: Option<History>,
+    /// infer scope from changed paths.
+    scope: Option<ScopeRule>,
+    /// language of generated text. e.g. `ja`, `Japanese`
+    lang: Option<String>,
+    /// secrets in requests to cloud providers.
+    redact: Option<RedactRule>,
+    /// providers allowed to receive code.
+    policy: Option<Policy>,
+    /// provider -> command printing its api key. e.g. `pass show gemini`
+    key_command: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Getters)]
+pub struct Policy {
+    /// e.g. `["ollama"]`. all providers when not set.
+    allowed_providers: Option<Vec<String>>,
+    /// only providers on this machine. (ollama)
+    local_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Getters)]
+pub struct RedactRule {
+    /// kind -> regex. with a `secret` group, only the group is replaced.
+    patterns: Option<HashMap<String, String>>,
+    /// detect high-entropy strings. (default true)
+    entropy: Option<bool>,
+    /// refuse to send instead of redacting. (default false)
+    refuse: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Getters)]
+pub struct ScopeRule {
+    /// path prefix -> scope. takes precedence over workspace members.
+    paths: Option<HashMap<String, String>>,
+    /// members of Cargo or npm workspace are scopes. (default true)
+    workspace: Option<bool>,
+    /// top-level directory is the scope of other paths. (default false)
+    top_level_dir: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Getters)]
+pub struct History {
+    /// number of recent commit msgs as examples. 0 disables. (default 10)
+    samples: Option<usize>,
+    /// use only scopes found in history.
+    enforce_scopes: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssueRule {
+    /// regex. the first capture group (or the whole match) is the id.
+    pub pattern: String,
+    /// e.g. `Refs: {id}`, `Closes #{id}`
+    pub footer: String,
 }
 
 impl<P: AsRef<Path>> Storage<P> for Config {}
 
+impl Config {
+    /// prompt template of task set in config.
+    pub fn template(&self, task: &str) -> Option<&String> {
+        self.prompt.as_ref()?.templates.as_ref()?.get(task)
+    }
+
+    /// preset of `cst` by name.
+    pub fn custom_prompt(&self, name: &str) -> Option<&String> {
+        self.prompt.as_ref()?.custom_prompt.get(name)
+    }
+
+    pub fn custom_prompts(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.prompt.iter().flat_map(|p| p.custom_prompt.iter())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Prompt {
+    #[serde(default)]
     custom_prompt: HashMap<String, String>,
+    /// task name (`cmt`, `sum`, ...) -> template
+    templates: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,79 @@
+/// header of Conventional Commits. `type(scope)!: description`
+#[derive(Debug, PartialEq)]
+pub struct Header<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub desc: &'a str,
+}
+
+pub fn parse_header(msg: &str) -> Option<Header<'_>> {
+    let line = msg.lines().next()?.trim();
+    let (prefix, desc) = line.split_once(": ")?;
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(p) => (p, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((k, s)) => (k, Some(s.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(Header {
+        kind,
+        scope: scope.filter(|s| !s.is_empty()),
+        breaking,
+        desc,
+    })
+}
+
+/// replace scope in the first line. `None` removes it. other lines are kept.
+pub fn set_scope(msg: &str, scope: Option<&str>) -> String {
+    let Some(h) = parse_header(msg) else {
+        return msg.to_string();
+    };
+    let header = format!(
+        "{}{}{}: {}",
+        h.kind,
+        scope.map(|s| format!("({s})")).unwrap_or_default(),
+        if h.breaking { "!" } else { "" },
+        h.desc
+    );
+    match msg.split_once('\n') {
+        Some((_, rest)) => format!("{header}\n{rest}"),
+        None => header,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Header, parse_header, set_scope};
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("feat(git)!: add split\n\nbody"),
+            Some(Header {
+                kind: "feat",
+                scope: Some("git"),
+                breaking: true,
+                desc: "add split"
+            })
+        );
+        assert_eq!(parse_header("fix: typo").unwrap().scope, None);
+        assert!(parse_header("Update README").is_none());
+        assert!(parse_header("Merge branch 'main': foo").is_none());
+    }
+
+    #[test]
+    fn test_set_scope() {
+        assert_eq!(
+            set_scope("feat(foo): add bar\n\nbody", Some("git")),
+            "feat(git): add bar\n\nbody"
+        );
+        assert_eq!(set_scope("fix(foo)!: bar", None), "fix!: bar");
+        assert_eq!(set_scope("not conventional", None), "not conventional");
+    }
+}
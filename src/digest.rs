@@ -0,0 +1,373 @@
+use std::{fs, path::Path};
+
+use crate::Error;
+
+/// default of `--budget`.
+pub const DEFAULT_BUDGET: usize = 12_000;
+/// rough estimate for english and code.
+const CHARS_PER_TOKEN: usize = 4;
+/// manifests and README in the digest are cut at this length.
+const MAX_SECTION_CHARS: usize = 4_000;
+
+const MANIFESTS: [&str; 5] = [
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+    "deno.json",
+];
+const ENTRY_POINTS: [&str; 9] = [
+    "main.rs",
+    "lib.rs",
+    "main.py",
+    "__main__.py",
+    "app.py",
+    "index.js",
+    "index.ts",
+    "main.ts",
+    "main.go",
+];
+const LICENSE_FILES: [&str; 5] = [
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.txt",
+    "LICENSE-MIT",
+    "COPYING",
+];
+
+/// what is sent for README generation.
+pub struct Context {
+    /// overview of the project.
+    pub digest: String,
+    /// full contents of files within the budget.
+    pub contents: String,
+    pub included: Vec<String>,
+    /// files over the budget.
+    pub omitted: Vec<String>,
+}
+
+impl Context {
+    pub fn text(&self) -> String {
+        if self.contents.is_empty() {
+            self.digest.clone()
+        } else {
+            format!("{}\n# file contents\n\n{}", self.digest, self.contents)
+        }
+    }
+
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.text())
+    }
+}
+
+fn estimate_tokens(s: &str) -> usize {
+    s.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+fn cut(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}\n...(cut)", &s[..i]),
+        None => s.to_string(),
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or(path)
+}
+
+fn is_entry_point(path: &str) -> bool {
+    ENTRY_POINTS.contains(&file_name(path)) || path.contains("/bin/")
+}
+
+/// SPDX id from the text of a license file.
+pub fn detect_license(text: &str) -> Option<&'static str> {
+    let has = |s: &str| text.contains(s);
+    if has("Apache License") && has("Version 2.0") {
+        Some("Apache-2.0")
+    } else if has("MIT License") || has("Permission is hereby granted, free of charge") {
+        Some("MIT")
+    } else if has("GNU LESSER GENERAL PUBLIC LICENSE") {
+        Some("LGPL")
+    } else if has("GNU AFFERO GENERAL PUBLIC LICENSE") {
+        Some("AGPL-3.0")
+    } else if has("GNU GENERAL PUBLIC LICENSE") {
+        Some(if has("Version 3") {
+            "GPL-3.0"
+        } else {
+            "GPL-2.0"
+        })
+    } else if has("Mozilla Public License Version 2.0") {
+        Some("MPL-2.0")
+    } else if has("Redistribution and use in source and binary forms") {
+        Some("BSD")
+    } else if has("This is free and unencumbered software") {
+        Some("Unlicense")
+    } else {
+        None
+    }
+}
+
+/// `license` field of Cargo.toml or package.json.
+fn manifest_license(name: &str, text: &str) -> Option<String> {
+    match name {
+        "Cargo.toml" => text
+            .parse::<toml::Table>()
+            .ok()?
+            .get("package")?
+            .get("license")?
+            .as_str()
+            .map(|s| s.to_string()),
+        "package.json" => serde_json::from_str::<serde_json::Value>(text).ok()?["license"]
+            .as_str()
+            .map(|s| s.to_string()),
+        _ => None,
+    }
+}
+
+/// first line of public items. rust, js/ts and python.
+pub fn public_api(path: &str, code: &str) -> Vec<String> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    code.lines()
+        .filter(|l| match ext {
+            "rs" => {
+                let l = l.trim_start();
+                l.starts_with("pub ") && !l.starts_with("pub use ")
+            }
+            "js" | "ts" | "mjs" => l.starts_with("export "),
+            "py" => l
+                .strip_prefix("def ")
+                .or_else(|| l.strip_prefix("class "))
+                .is_some_and(|name| !name.starts_with('_')),
+            _ => false,
+        })
+        .map(|l| {
+            l.trim()
+                .trim_end_matches(['{', ';', ':'])
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// items with `#[derive(Parser)]`, `Subcommand`, `Args` or `ValueEnum` of clap.
+pub fn cli_definitions(code: &str) -> Vec<String> {
+    let lines = code.lines().collect::<Vec<_>>();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let l = lines[i];
+        let is_cli = l.contains("derive(")
+            && ["Parser", "Subcommand", "Args", "ValueEnum"]
+                .iter()
+                .any(|d| l.contains(d));
+        if !is_cli {
+            i += 1;
+            continue;
+        }
+        let (start, mut depth, mut opened) = (i, 0i32, false);
+        while i < lines.len() {
+            depth += lines[i].matches('{').count() as i32 - lines[i].matches('}').count() as i32;
+            opened |= lines[i].contains('{');
+            i += 1;
+            // unit struct or closed item
+            if (opened && depth <= 0) || (!opened && lines[i - 1].trim_end().ends_with(';')) {
+                break;
+            }
+        }
+        blocks.push(lines[start..i].join("\n"));
+    }
+    blocks
+}
+
+/// digest of the project, then full contents of `files` by priority until `budget` tokens.
+/// priority: entry points, files with cli definitions, then shallow and small files.
+pub fn build<P: AsRef<Path>>(
+    project_path: P,
+    files: &[String],
+    budget: usize,
+) -> Result<Context, Error> {
+    let root = project_path.as_ref();
+    let rel = |p: &str| {
+        Path::new(p)
+            .strip_prefix(root)
+            .map(|r| r.to_string_lossy().to_string())
+            .unwrap_or_else(|_| p.trim_start_matches("./").to_string())
+    };
+    let mut sections = Vec::new();
+    let mut licenses = Vec::new();
+
+    let mut manifests = Vec::new();
+    for name in MANIFESTS {
+        if let Ok(text) = fs::read_to_string(root.join(name)) {
+            licenses.extend(manifest_license(name, &text).map(|l| format!("{l} ({name})")));
+            manifests.push(format!("path: {name}\n{}", cut(&text, MAX_SECTION_CHARS)));
+        }
+    }
+    if !manifests.is_empty() {
+        sections.push(format!("# manifests\n\n{}", manifests.join("\n\n")));
+    }
+
+    for name in LICENSE_FILES {
+        if let Ok(text) = fs::read_to_string(root.join(name)) {
+            let id = detect_license(&text).unwrap_or("unknown");
+            licenses.push(format!("{id} ({name})"));
+        }
+    }
+    if !licenses.is_empty() {
+        sections.push(format!("# license\n\n{}", licenses.join("\n")));
+    }
+
+    // readable files only
+    let mut codes = files
+        .iter()
+        .filter_map(|p| Some((rel(p), fs::read_to_string(p).ok()?)))
+        .filter(|(p, _)| !MANIFESTS.contains(&p.as_str()) && file_name(p) != "README.md")
+        .collect::<Vec<_>>();
+
+    let entries = codes
+        .iter()
+        .filter(|(p, _)| is_entry_point(p))
+        .map(|(p, _)| p.clone())
+        .collect::<Vec<_>>();
+    if !entries.is_empty() {
+        sections.push(format!("# entry points\n\n{}", entries.join("\n")));
+    }
+
+    let api = codes
+        .iter()
+        .filter_map(|(p, c)| {
+            let items = public_api(p, c);
+            (!items.is_empty()).then(|| format!("{p}\n  {}", items.join("\n  ")))
+        })
+        .collect::<Vec<_>>();
+    if !api.is_empty() {
+        sections.push(format!("# public api\n\n{}", api.join("\n")));
+    }
+
+    let cli = codes
+        .iter()
+        .filter(|(p, _)| p.ends_with(".rs"))
+        .filter_map(|(p, c)| {
+            let defs = cli_definitions(c);
+            (!defs.is_empty()).then(|| format!("path: {p}\n{}", defs.join("\n\n")))
+        })
+        .collect::<Vec<_>>();
+    if !cli.is_empty() {
+        sections.push(format!("# cli definitions\n\n{}", cli.join("\n\n")));
+    }
+
+    if let Ok(text) = fs::read_to_string(root.join("README.md")) {
+        sections.push(format!(
+            "# existing README\n\n{}",
+            cut(&text, MAX_SECTION_CHARS)
+        ));
+    }
+
+    let digest = sections.join("\n\n") + "\n";
+
+    codes.sort_by_key(|(p, c)| {
+        let rank = if is_entry_point(p) {
+            0
+        } else if !cli_definitions(c).is_empty() {
+            1
+        } else {
+            2
+        };
+        (rank, p.matches('/').count(), c.len())
+    });
+    let mut used = estimate_tokens(&digest);
+    let (mut contents, mut included, mut omitted) = (String::new(), Vec::new(), Vec::new());
+    for (p, c) in codes {
+        let block = format!("path: {p}\ncontents:\n{c}\n");
+        let tokens = estimate_tokens(&block);
+        // smaller files later may still fit
+        if used + tokens > budget {
+            omitted.push(p);
+            continue;
+        }
+        used += tokens;
+        contents.push_str(&block);
+        included.push(p);
+    }
+    Ok(Context {
+        digest,
+        contents,
+        included,
+        omitted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{build, cli_definitions, detect_license, public_api};
+
+    #[test]
+    fn test_extract() {
+        let code = "use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[command(name = \"ggw\")]
+pub struct Cli {
+    #[arg(short = 'm')]
+    model: Option<String>,
+}
+
+pub fn run(cli: &Cli) -> Result<(), Error> {
+    fn inner() {}
+}
+pub(crate) fn helper() {}
+";
+        assert_eq!(
+            public_api("src/main.rs", code),
+            [
+                "pub struct Cli",
+                "pub fn run(cli: &Cli) -> Result<(), Error>"
+            ]
+        );
+        let defs = cli_definitions(code);
+        assert_eq!(defs.len(), 1);
+        assert!(defs[0].starts_with("#[derive(Debug, Parser)]"));
+        assert!(defs[0].ends_with("model: Option<String>,\n}"));
+        assert_eq!(
+            public_api("a.py", "def run():\ndef _hidden():"),
+            ["def run()"]
+        );
+
+        assert_eq!(
+            detect_license("Apache License\nVersion 2.0, January 2004"),
+            Some("Apache-2.0")
+        );
+        assert_eq!(detect_license("MIT License\n\nCopyright"), Some("MIT"));
+        assert_eq!(detect_license("all rights reserved"), None);
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = env::temp_dir().join(format!("ggw_digest_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"other\"\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "# other\n").unwrap();
+        fs::write(dir.join("src/main.rs"), "pub fn run() {}\n").unwrap();
+
+        let files = vec![dir.join("src/main.rs").to_string_lossy().to_string()];
+        let context = build(&dir, &files, 1000).unwrap();
+        assert!(context.digest.contains("name = \"other\""));
+        assert!(context.digest.contains("MIT (Cargo.toml)"));
+        assert!(context.digest.contains("# existing README\n\n# other"));
+        assert_eq!(context.included, ["src/main.rs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use std::{env, fs, path::Path, process::Command};
+
+use crate::{Error, git};
+
+const TEMPLATE_HELP: &str = "# Please enter the commit message for your changes. Lines starting
+# with '#' will be ignored, and an empty message aborts the commit.";
+
+/// open commit msg in editor like `git commit -v`.
+/// returns the msg removed comment lines.
+pub fn edit_cmt_msg<P: AsRef<Path>, T: AsRef<str>>(
+    project_path: P,
+    msg: T,
+) -> Result<String, Error> {
+    let (files, stat) = git::get_diff_stat(&project_path)?;
+    let cleaned = edit_text(
+        &project_path,
+        "COMMIT_EDITMSG",
+        build_template(msg, &files, &stat),
+    )?;
+    if cleaned.is_empty() {
+        Err(Error::EmptyCmtMsg)
+    } else {
+        Ok(cleaned)
+    }
+}
+
+/// write `text` to `.git/<file_name>` and open it in editor.
+/// returns the edited text removed comment lines.
+pub fn edit_text<P: AsRef<Path>, T: AsRef<str>>(
+    project_path: P,
+    file_name: &str,
+    text: T,
+) -> Result<String, Error> {
+    let edit_path = git::git_dir(&project_path)?.join(file_name);
+
+    fs::write(&edit_path, text.as_ref()).map_err(Error::IoE)?;
+    open_editor(resolve_editor(&project_path), &edit_path)?;
+
+    let edited = fs::read_to_string(&edit_path).map_err(Error::IoE)?;
+    Ok(cleanup_msg(edited))
+}
+
+/// same order as git. GIT_EDITOR > core.editor > VISUAL > EDITOR > vi
+fn resolve_editor<P: AsRef<Path>>(project_path: P) -> String {
+    env::var("GIT_EDITOR")
+        .ok()
+        .or_else(|| git::get_editor(project_path))
+        .or_else(|| env::var("VISUAL").ok())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+fn open_editor<T: AsRef<str>, P: AsRef<Path>>(editor: T, path: P) -> Result<(), Error> {
+    // allow editor with args. e.g. `code --wait`
+    let mut args = editor.as_ref().split_whitespace();
+    let program = args
+        .next()
+        .ok_or(Error::Editor(editor.as_ref().to_string()))?;
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(path.as_ref())
+        .status()
+        .map_err(Error::IoE)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Editor(editor.as_ref().to_string()))
+    }
+}
+
+fn build_template<T: AsRef<str>>(msg: T, files: &[String], stat: &str) -> String {
+    let mut t = format!(
+        "{}\n\n{TEMPLATE_HELP}\n#\n# Changes to be committed:\n",
+        msg.as_ref().trim()
+    );
+    files.iter().for_each(|f| t.push_str(&format!("#\t{f}\n")));
+    t.push_str("#\n");
+    stat.lines().for_each(|l| t.push_str(&format!("# {l}\n")));
+    t
+}
+
+/// remove `#` lines, trailing spaces and extra blank lines. (git's `--cleanup=strip`)
+pub fn cleanup_msg<T: AsRef<str>>(msg: T) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for l in msg.as_ref().lines() {
+        if l.starts_with('#') {
+            continue;
+        }
+        let l = l.trim_end();
+        if l.is_empty() && lines.last().is_none_or(|p| p.is_empty()) {
+            continue;
+        }
+        lines.push(l);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_template, cleanup_msg};
+
+    #[test]
+    fn test_cleanup_template() {
+        let t = build_template(
+            "feat: add editor\n\nbody",
+            &["modified:   src/main.rs".to_string()],
+            " src/main.rs | 2 +-\n 1 file changed",
+        );
+        assert_eq!(cleanup_msg(t), "feat: add editor\n\nbody");
+    }
+
+    #[test]
+    fn test_cleanup_empty() {
+        assert_eq!(cleanup_msg("\n# comment\n\n#\n"), "");
+    }
+}
@@ -0,0 +1,139 @@
+use std::{cmp::Reverse, collections::HashMap, path::Path};
+
+use crate::{
+    Error, Model,
+    git::{self, CommitInfo},
+    guard, llm,
+    template::Template,
+};
+
+pub const FIXUP_PMT: &str = "You are an assistant that finds which earlier commit a change belongs to.
+Given a list of candidate commits (short sha, score, subject and touched files) and a diff, choose the single commit the diff should be squashed into.
+The score counts lines of the diff last modified by the commit (blame) and files shared with the commit. A higher score is a strong hint, but use the subjects to decide.
+Output only the short sha of the chosen commit. Do not include any extra text.
+{{hints}}{{user}}Candidates:
+{{candidates}}
+Changes:
+{{diff}}
+Output only the short sha of the candidate the changes above belong to.";
+
+/// number of recent commits to look for.
+const SEARCH_DEPTH: usize = 20;
+/// number of candidates sent to llm.
+const MAX_CANDIDATES: usize = 5;
+
+/// suggest a commit which staged changes should be `fixup!` to.
+pub fn suggest_fixup<P: AsRef<Path>, T: AsRef<str>>(
+    project_path: P,
+    template: Template,
+    model: Model,
+    api_key: Option<T>,
+) -> Result<CommitInfo, Error> {
+    let diff = git::get_staged_diff(&project_path)?;
+    let candidates = rank_candidates(
+        git::recent_commits(&project_path, SEARCH_DEPTH)?,
+        &git::staged_paths(&project_path)?,
+        &git::blame_staged_lines(&project_path)?,
+    );
+    if candidates.is_empty() {
+        return Err(Error::NotFoundFixupTarget);
+    }
+
+    let list = candidates
+        .iter()
+        .map(|(c, score)| {
+            format!(
+                "{} (score: {score}) {}\n  files: {}",
+                c.short_id,
+                c.summary,
+                c.files.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (system, pmt) = template
+        .var("candidates", guard::fence("candidates", list))
+        .var("diff", guard::fence("diff", diff))
+        .render_parts();
+
+    let res = llm::call_llm(
+        system,
+        pmt,
+        model.provider,
+        model.model,
+        api_key.map(|f| f.as_ref().to_string()),
+        None,
+        None,
+    )
+    .map_err(Error::Llm)?;
+
+    Ok(pick_candidate(res, candidates))
+}
+
+/// score = blamed lines * 2 + shared files.
+/// returns top candidates by score. if all scores are zero, the most recent ones.
+fn rank_candidates(
+    commits: Vec<CommitInfo>,
+    changed: &[String],
+    blamed: &HashMap<String, usize>,
+) -> Vec<(CommitInfo, usize)> {
+    let mut scored = commits
+        .into_iter()
+        .map(|c| {
+            let overlap = c.files.iter().filter(|f| changed.contains(f)).count();
+            let score = blamed.get(&c.id).copied().unwrap_or(0) * 2 + overlap;
+            (c, score)
+        })
+        .collect::<Vec<_>>();
+    // stable sort keeps the recent order for the same score
+    scored.sort_by_key(|c| Reverse(c.1));
+    scored.truncate(MAX_CANDIDATES);
+    scored
+}
+
+/// find the candidate named in llm's answer. fallback to the best score.
+fn pick_candidate<T: AsRef<str>>(res: T, mut candidates: Vec<(CommitInfo, usize)>) -> CommitInfo {
+    let idx = candidates
+        .iter()
+        .position(|(c, _)| res.as_ref().contains(&c.short_id))
+        .unwrap_or(0);
+    candidates.swap_remove(idx).0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{pick_candidate, rank_candidates};
+    use crate::git::CommitInfo;
+
+    fn info(id: &str, files: &[&str]) -> CommitInfo {
+        CommitInfo {
+            id: format!("{id}000000"),
+            short_id: id.to_string(),
+            summary: format!("commit {id}"),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_rank_and_pick() {
+        let commits = vec![
+            info("aaaaaaa", &["README.md"]),
+            info("bbbbbbb", &["src/git.rs"]),
+            info("ccccccc", &["src/main.rs", "src/git.rs"]),
+        ];
+        let blamed = HashMap::from([("ccccccc000000".to_string(), 3)]);
+        let ranked = rank_candidates(commits, &["src/git.rs".to_string()], &blamed);
+        assert_eq!(ranked[0].0.short_id, "ccccccc");
+        assert_eq!(ranked[1].0.short_id, "bbbbbbb");
+
+        assert_eq!(pick_candidate("bbbbbbb\n", ranked).short_id, "bbbbbbb");
+    }
+
+    #[test]
+    fn test_pick_fallback() {
+        let ranked = vec![(info("aaaaaaa", &[]), 2), (info("bbbbbbb", &[]), 1)];
+        assert_eq!(pick_candidate("I don't know", ranked).short_id, "aaaaaaa");
+    }
+}
@@ -1,18 +1,101 @@
-use crate::Error;
-use git2::{DiffOptions, IndexAddOption, Repository, Signature};
-use std::path::Path;
+use crate::{Error, hook, sign};
+use git2::{
+    ApplyLocation, ApplyOptions, BlameOptions, Commit, Delta, Diff, DiffOptions, DiffStatsFormat,
+    IndexAddOption, Oid, Patch, Repository, ResetType, Signature, Tree,
+};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-pub fn get_diff<P: AsRef<Path>>(path: P) -> Result<String, Error> {
-    let repo = Repository::open(path).map_err(Error::GitE)?;
+pub struct CommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub files: Vec<String>,
+}
+
+pub struct Hunk {
+    pub path: String,
+    pub header: String,
+    /// hunk lines with `+`/`-`/` ` prefix.
+    pub patch: String,
+}
+
+impl Hunk {
+    /// identify hunk without line numbers. they shift after other hunks are committed.
+    fn key(&self) -> (&str, &str) {
+        (&self.path, &self.patch)
+    }
+}
+
+/// name and email of author or committer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub email: String,
+}
+
+impl Ident {
+    /// parse `Name <email>`.
+    pub fn parse<T: AsRef<str>>(s: T) -> Option<Self> {
+        let (name, rest) = s.as_ref().trim().split_once('<')?;
+        let email = rest.strip_suffix('>')?.trim();
+        let name = name.trim();
+        if name.is_empty() || email.is_empty() {
+            None
+        } else {
+            Some(Self {
+                name: name.to_string(),
+                email: email.to_string(),
+            })
+        }
+    }
+
+    fn signature(&self) -> Result<Signature<'static>, Error> {
+        Signature::now(&self.name, &self.email).map_err(Error::GitE)
+    }
+}
+
+impl std::fmt::Display for Ident {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+/// HEAD and index before `split`.
+pub struct IndexBackup {
+    head: Oid,
+    tree: Oid,
+}
+
+fn head_to_workdir(repo: &Repository) -> Result<Diff<'_>, Error> {
     let head_commit = repo
         .head()
         .map_err(Error::GitE)?
         .peel_to_commit()
         .map_err(Error::GitE)?;
     let head_tree = head_commit.tree().map_err(Error::GitE)?;
-    let diff = repo
-        .diff_tree_to_workdir(Some(&head_tree), Some(&mut DiffOptions::new()))
+    repo.diff_tree_to_workdir(Some(&head_tree), Some(&mut DiffOptions::new()))
+        .map_err(Error::GitE)
+}
+
+/// HEAD's parent -> workdir. the change of amended commit.
+fn parent_to_workdir(repo: &Repository) -> Result<Diff<'_>, Error> {
+    let head_commit = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
         .map_err(Error::GitE)?;
+    repo.diff_tree_to_workdir(
+        parent_tree(&head_commit)?.as_ref(),
+        Some(&mut DiffOptions::new()),
+    )
+    .map_err(Error::GitE)
+}
+
+fn diff_to_string(diff: &Diff) -> Result<String, Error> {
     let mut pa = String::new();
     diff.print(git2::DiffFormat::Patch, |_, _, line| {
         if let Ok(t) = std::str::from_utf8(line.content()) {
@@ -24,21 +107,522 @@ pub fn get_diff<P: AsRef<Path>>(path: P) -> Result<String, Error> {
     Ok(pa)
 }
 
-pub fn git_commit<P: AsRef<Path>, M: AsRef<str>, T: AsRef<str>>(
+/// unified diff of two texts, for preview before writing a file. empty when same.
+pub fn unified_diff<T: AsRef<str>, P: AsRef<Path>>(
+    old: T,
+    new: T,
     path: P,
-    msg: &M,
-    name: T,
-    email: T,
-) -> Result<(), Error> {
+) -> Result<String, Error> {
+    let path = path.as_ref();
+    let mut patch = Patch::from_buffers(
+        old.as_ref().as_bytes(),
+        Some(path),
+        new.as_ref().as_bytes(),
+        Some(path),
+        None,
+    )
+    .map_err(Error::GitE)?;
+    if patch.num_hunks() == 0 {
+        return Ok(String::new());
+    }
+    let buf = patch.to_buf().map_err(Error::GitE)?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+fn diff_paths(diff: &Diff) -> Vec<String> {
+    diff.deltas()
+        .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
+
+/// parent tree of commit. `None` for root commit.
+fn parent_tree<'r>(commit: &Commit<'r>) -> Result<Option<Tree<'r>>, Error> {
+    if commit.parent_count() == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(
+            commit
+                .parent(0)
+                .and_then(|p| p.tree())
+                .map_err(Error::GitE)?,
+        ))
+    }
+}
+
+/// include untracked files as added hunks.
+fn hunk_diff_opts() -> DiffOptions {
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    opts
+}
+
+/// hunks grouped per delta. binary files have no hunks.
+fn collect_hunks(diff: &Diff) -> Result<Vec<Vec<Hunk>>, Error> {
+    let mut deltas = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let mut hunks = Vec::new();
+        if let Some(patch) = Patch::from_diff(diff, idx).map_err(Error::GitE)? {
+            let delta = patch.delta();
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            for h in 0..patch.num_hunks() {
+                let (hunk, lines) = patch.hunk(h).map_err(Error::GitE)?;
+                let header = String::from_utf8_lossy(hunk.header())
+                    .trim_end()
+                    .to_string();
+                let mut text = String::new();
+                for l in 0..lines {
+                    let line = patch.line_in_hunk(h, l).map_err(Error::GitE)?;
+                    if matches!(line.origin(), '+' | '-' | ' ') {
+                        text.push(line.origin());
+                    }
+                    text.push_str(&String::from_utf8_lossy(line.content()));
+                }
+                hunks.push(Hunk {
+                    path: path.clone(),
+                    header,
+                    patch: text,
+                });
+            }
+        }
+        deltas.push(hunks);
+    }
+    Ok(deltas)
+}
+
+pub fn get_diff<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let diff = head_to_workdir(&repo)?;
+    diff_to_string(&diff)
+}
+
+/// diff HEAD to index. what `git commit` without `-a` commits.
+pub fn get_staged_diff<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head_tree = repo
+        .head()
+        .and_then(|h| h.peel_to_tree())
+        .map_err(Error::GitE)?;
+    let diff = repo
+        .diff_tree_to_index(Some(&head_tree), None, None)
+        .map_err(Error::GitE)?;
+    diff_to_string(&diff)
+}
+
+/// diff HEAD's parent to workdir. use for `--amend`.
+pub fn get_amend_diff<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    diff_to_string(&parent_to_workdir(&repo)?)
+}
+
+/// paths changed by HEAD and the current changes.
+pub fn amend_paths<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    Ok(diff_paths(&parent_to_workdir(&repo)?))
+}
+
+/// diff of the commit itself. (parent -> commit)
+pub fn get_commit_diff<P: AsRef<Path>, T: AsRef<str>>(path: P, rev: T) -> Result<String, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let commit = repo
+        .revparse_single(rev.as_ref())
+        .and_then(|o| o.peel_to_commit())
+        .map_err(Error::GitE)?;
+    let tree = commit.tree().map_err(Error::GitE)?;
+    let diff = repo
+        .diff_tree_to_tree(parent_tree(&commit)?.as_ref(), Some(&tree), None)
+        .map_err(Error::GitE)?;
+    diff_to_string(&diff)
+}
+
+/// last `n` commits from HEAD with touched files.
+pub fn recent_commits<P: AsRef<Path>>(path: P, n: usize) -> Result<Vec<CommitInfo>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let mut walk = repo.revwalk().map_err(Error::GitE)?;
+    walk.push_head().map_err(Error::GitE)?;
+
+    let mut list = Vec::new();
+    for id in walk.take(n) {
+        let commit = id
+            .and_then(|id| repo.find_commit(id))
+            .map_err(Error::GitE)?;
+        let tree = commit.tree().map_err(Error::GitE)?;
+        let diff = repo
+            .diff_tree_to_tree(parent_tree(&commit)?.as_ref(), Some(&tree), None)
+            .map_err(Error::GitE)?;
+        list.push(CommitInfo {
+            id: commit.id().to_string(),
+            short_id: commit
+                .as_object()
+                .short_id()
+                .map_err(Error::GitE)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            files: diff_paths(&diff),
+        });
+    }
+    Ok(list)
+}
+
+/// every hunk of HEAD -> workdir, include untracked files.
+pub fn get_hunks<P: AsRef<Path>>(path: P) -> Result<Vec<Hunk>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head_tree = repo
+        .head()
+        .and_then(|h| h.peel_to_tree())
+        .map_err(Error::GitE)?;
+    let diff = repo
+        .diff_tree_to_workdir(Some(&head_tree), Some(&mut hunk_diff_opts()))
+        .map_err(Error::GitE)?;
+    Ok(collect_hunks(&diff)?.into_iter().flatten().collect())
+}
+
+/// subjects of last `n` commits from HEAD. merge and `fixup!` commits are skipped.
+pub fn recent_summaries<P: AsRef<Path>>(path: P, n: usize) -> Result<Vec<String>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let mut walk = repo.revwalk().map_err(Error::GitE)?;
+    walk.push_head().map_err(Error::GitE)?;
+
+    let mut list = Vec::new();
+    for id in walk {
+        if list.len() >= n {
+            break;
+        }
+        let commit = id
+            .and_then(|id| repo.find_commit(id))
+            .map_err(Error::GitE)?;
+        let summary = commit.summary().unwrap_or_default();
+        if commit.parent_count() > 1
+            || summary.starts_with("fixup!")
+            || summary.starts_with("squash!")
+        {
+            continue;
+        }
+        list.push(summary.to_string());
+    }
+    Ok(list)
+}
+
+pub fn head_id<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    repo.head()
+        .and_then(|h| h.peel_to_commit())
+        .map(|c| c.id().to_string())
+        .map_err(Error::GitE)
+}
+
+/// paths staged in index. all of them before the first commit.
+pub fn staged_paths<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head_tree = match repo.head() {
+        Ok(h) => Some(h.peel_to_tree().map_err(Error::GitE)?),
+        Err(_) => None,
+    };
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), None, None)
+        .map_err(Error::GitE)?;
+    Ok(diff_paths(&diff))
+}
+
+pub fn changed_paths<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let diff = head_to_workdir(&repo)?;
+    Ok(diff_paths(&diff))
+}
+
+/// blame the lines that staged changes modify or delete.
+/// returns `commit id -> line count`.
+pub fn blame_staged_lines<P: AsRef<Path>>(path: P) -> Result<HashMap<String, usize>, Error> {
     let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head_tree = repo
+        .head()
+        .and_then(|h| h.peel_to_tree())
+        .map_err(Error::GitE)?;
+    // context lines are not changed. they would count the commits of nearby code.
+    let diff = repo
+        .diff_tree_to_index(
+            Some(&head_tree),
+            None,
+            Some(DiffOptions::new().context_lines(0)),
+        )
+        .map_err(Error::GitE)?;
+
+    // (old path, old start, old lines)
+    let mut hunks: Vec<(PathBuf, u32, u32)> = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |d, h| {
+            if let Some(p) = d.old_file().path().filter(|_| h.old_lines() > 0) {
+                hunks.push((p.to_path_buf(), h.old_start(), h.old_lines()));
+            }
+            true
+        }),
+        None,
+    )
+    .map_err(Error::GitE)?;
+
+    let mut counts = HashMap::new();
+    let mut blames = HashMap::new();
+    for (p, start, lines) in hunks {
+        if !blames.contains_key(&p) {
+            match repo.blame_file(&p, Some(&mut BlameOptions::new())) {
+                Ok(b) => {
+                    blames.insert(p.clone(), b);
+                }
+                // new or binary file
+                Err(_) => continue,
+            }
+        }
+        let Some(blame) = blames.get(&p) else {
+            continue;
+        };
+        for l in start..start + lines {
+            if let Some(h) = blame.get_line(l as usize) {
+                *counts.entry(h.final_commit_id().to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// returns changed files like `modified:   src/main.rs` and `git diff --stat` text.
+pub fn get_diff_stat<P: AsRef<Path>>(path: P) -> Result<(Vec<String>, String), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let diff = head_to_workdir(&repo)?;
+
+    let files = diff
+        .deltas()
+        .map(|d| {
+            let status = match d.status() {
+                Delta::Added => "new file",
+                Delta::Deleted => "deleted",
+                Delta::Renamed => "renamed",
+                Delta::Copied => "copied",
+                Delta::Typechange => "typechange",
+                _ => "modified",
+            };
+            let p = d
+                .new_file()
+                .path()
+                .or_else(|| d.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            format!("{status}:   {p}")
+        })
+        .collect::<Vec<_>>();
+
+    let stat = diff
+        .stats()
+        .and_then(|s| s.to_buf(DiffStatsFormat::FULL, 80))
+        .map_err(Error::GitE)?;
+
+    Ok((files, stat.as_str().unwrap_or_default().to_string()))
+}
+
+/// short name of the branch HEAD points. `None` when detached.
+pub fn current_branch<P: AsRef<Path>>(path: P) -> Option<String> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.find_reference("HEAD").ok()?;
+    head.symbolic_target()?
+        .strip_prefix("refs/heads/")
+        .map(|b| b.to_string())
+}
+
+/// path of `.git` dir.
+pub fn git_dir<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    Ok(repo.path().to_path_buf())
+}
+
+/// `core.editor` in repo config (include global).
+pub fn get_editor<P: AsRef<Path>>(path: P) -> Option<String> {
+    get_config(path, "core.editor")
+}
+
+/// a value of config (include global) of the repo containing `path`, found upward like git. e.g. `ggw.lang`
+pub fn get_config<P: AsRef<Path>>(path: P, key: &str) -> Option<String> {
+    Repository::discover(path)
+        .ok()?
+        .config()
+        .ok()?
+        .get_string(key)
+        .ok()
+}
+
+/// `true`, `yes`, `on` or `1` is true, like git.
+pub fn get_config_bool<P: AsRef<Path>>(path: P, key: &str) -> Option<bool> {
+    Repository::discover(path)
+        .ok()?
+        .config()
+        .ok()?
+        .get_bool(key)
+        .ok()
+}
+
+fn stage_all(repo: &Repository) -> Result<(), Error> {
     let mut index = repo.index().map_err(Error::GitE)?;
     index
         .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
         .map_err(Error::GitE)?;
-    index.write().map_err(Error::GitE)?;
+    index.write().map_err(Error::GitE)
+}
 
+/// tree of the index on disk. pre-commit hooks may have re-staged files.
+fn index_tree(repo: &Repository) -> Result<Tree<'_>, Error> {
+    let mut index = repo.index().map_err(Error::GitE)?;
+    index.read(true).map_err(Error::GitE)?;
     let tree_id = index.write_tree().map_err(Error::GitE)?;
-    let tree = repo.find_tree(tree_id).map_err(Error::GitE)?;
+    repo.find_tree(tree_id).map_err(Error::GitE)
+}
+
+/// write commit object. signed when `commit.gpgsign` is true.
+/// move HEAD (or its branch) to the commit if `update_head`.
+fn create_commit(
+    repo: &Repository,
+    update_head: bool,
+    author: &Signature,
+    committer: &Signature,
+    msg: &str,
+    tree: &Tree,
+    parents: &[&Commit],
+) -> Result<Oid, Error> {
+    let sign = repo
+        .config()
+        .and_then(|c| c.get_bool("commit.gpgsign"))
+        .unwrap_or(false);
+    if !sign {
+        return repo
+            .commit(
+                update_head.then_some("HEAD"),
+                author,
+                committer,
+                msg,
+                tree,
+                parents,
+            )
+            .map_err(Error::GitE);
+    }
+
+    let buf = repo
+        .commit_create_buffer(author, committer, msg, tree, parents)
+        .map_err(Error::GitE)?;
+    let buf = buf
+        .as_str()
+        .ok_or(Error::Sign("commit is not valid utf-8".to_string()))?;
+    let signature = sign::sign_buffer(repo, buf, committer)?;
+    let id = repo
+        .commit_signed(buf, &signature, None)
+        .map_err(Error::GitE)?;
+    if update_head {
+        move_head(
+            repo,
+            id,
+            &format!("commit: {}", msg.lines().next().unwrap_or_default()),
+        )?;
+    }
+    Ok(id)
+}
+
+/// update the branch HEAD points. (or HEAD itself when detached)
+fn move_head(repo: &Repository, id: Oid, log_msg: &str) -> Result<(), Error> {
+    let head = repo.find_reference("HEAD").map_err(Error::GitE)?;
+    match head.symbolic_target() {
+        Some(branch) => repo
+            .reference(branch, id, true, log_msg)
+            .map(|_| ())
+            .map_err(Error::GitE),
+        None => repo.set_head_detached(id).map_err(Error::GitE),
+    }
+}
+
+/// prepare-commit-msg and commit-msg(`verify` only) hooks. returns msg edited by hooks.
+fn run_msg_hooks(repo: &Repository, msg: &str, verify: bool) -> Result<String, Error> {
+    let msg_path = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&msg_path, format!("{}\n", msg.trim_end())).map_err(Error::IoE)?;
+
+    hook::run_hook(
+        repo,
+        "prepare-commit-msg",
+        &[msg_path.as_os_str(), "message".as_ref()],
+    )?;
+    if verify {
+        hook::run_hook(repo, "commit-msg", &[&msg_path])?;
+    }
+    fs::read_to_string(&msg_path)
+        .map(|m| m.trim_end().to_string())
+        .map_err(Error::IoE)
+}
+
+/// run hooks and commit `tree` on HEAD like `git commit`.
+fn commit_on_head(
+    repo: &Repository,
+    msg: &str,
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+    tree: &Tree,
+    parents: &[&Commit],
+) -> Result<(), Error> {
+    let msg = run_msg_hooks(repo, msg, verify)?;
+    create_commit(
+        repo,
+        true,
+        &author.signature()?,
+        &committer.signature()?,
+        &msg,
+        tree,
+        parents,
+    )?;
+    // result of post-commit doesn't affect the commit
+    let _ = hook::run_hook::<&str>(repo, "post-commit", &[]);
+    Ok(())
+}
+
+pub fn git_commit<P: AsRef<Path>, M: AsRef<str>>(
+    path: P,
+    msg: &M,
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    stage_all(&repo)?;
+    commit_index(&repo, msg.as_ref(), author, committer, verify)
+}
+
+/// commit the index as it is, like `git commit` without `-a`.
+pub fn git_commit_staged<P: AsRef<Path>, M: AsRef<str>>(
+    path: P,
+    msg: &M,
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    commit_index(&repo, msg.as_ref(), author, committer, verify)
+}
+
+fn commit_index(
+    repo: &Repository,
+    msg: &str,
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    if verify {
+        hook::run_hook::<&str>(repo, "pre-commit", &[])?;
+    }
+    let tree = index_tree(repo)?;
 
     let parent_commit = repo
         .head()
@@ -46,40 +630,419 @@ pub fn git_commit<P: AsRef<Path>, M: AsRef<str>, T: AsRef<str>>(
         .and_then(|h| h.resolve().ok())
         .and_then(|r| r.peel_to_commit().ok());
 
-    let sig = Signature::now(name.as_ref(), email.as_ref()).map_err(Error::GitE)?;
-
-    let _commit_id = if let Some(pa) = parent_commit {
-        repo.commit(Some("HEAD"), &sig, &sig, msg.as_ref(), &tree, &[&pa])
-            .map_err(Error::GitE)?
+    if let Some(pa) = parent_commit {
+        commit_on_head(repo, msg, author, committer, verify, &tree, &[&pa])
     } else {
         // first commit
-        repo.commit(Some("HEAD"), &sig, &sig, msg.as_ref(), &tree, &[])
-            .map_err(Error::GitE)?
+        commit_on_head(repo, msg, author, committer, verify, &tree, &[])
+    }
+}
+
+/// replace HEAD with current workdir and new msg.
+/// keep original author unless `author` is given.
+pub fn git_commit_amend<P: AsRef<Path>, M: AsRef<str>>(
+    path: P,
+    msg: &M,
+    author: Option<&Ident>,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    stage_all(&repo)?;
+    if verify {
+        hook::run_hook::<&str>(&repo, "pre-commit", &[])?;
+    }
+    let tree = index_tree(&repo)?;
+    let head_commit = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(Error::GitE)?;
+    let parents = head_commit.parents().collect::<Vec<_>>();
+    let author = match author {
+        Some(a) => a.signature()?,
+        None => head_commit.author().to_owned(),
     };
 
+    let msg = run_msg_hooks(&repo, msg.as_ref(), verify)?;
+    create_commit(
+        &repo,
+        true,
+        &author,
+        &committer.signature()?,
+        &msg,
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )?;
+    let _ = hook::run_hook::<&str>(&repo, "post-commit", &[]);
     Ok(())
 }
 
-pub fn get_user_email() -> Result<(String, String), Error> {
-    let config = git2::Config::open_default().map_err(Error::GitE)?;
+/// rewrite msg of `rev` and re-create its descendants on top of it.
+/// only linear history between `rev` and HEAD is supported.
+/// keep original author unless `author` is given.
+pub fn reword_commit<P: AsRef<Path>, R: AsRef<str>, M: AsRef<str>>(
+    path: P,
+    rev: R,
+    msg: &M,
+    author: Option<&Ident>,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let target = repo
+        .revparse_single(rev.as_ref())
+        .and_then(|o| o.peel_to_commit())
+        .map_err(Error::GitE)?;
+    let sig = committer.signature()?;
+
+    // HEAD -> target (exclusive)
+    let mut descendants = Vec::new();
+    let mut cur = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(Error::GitE)?;
+    while cur.id() != target.id() {
+        if cur.parent_count() != 1 {
+            return Err(Error::CannotRewrite(format!(
+                "{} is a merge or root commit, and {} isn't reachable by linear history",
+                cur.id(),
+                rev.as_ref()
+            )));
+        }
+        let parent = cur.parent(0).map_err(Error::GitE)?;
+        descendants.push(cur);
+        cur = parent;
+    }
+
+    // same as `git rebase`, only commit-msg hook runs for the reworded commit.
+    let msg = run_msg_hooks(&repo, msg.as_ref(), verify)?;
+    let parents = target.parents().collect::<Vec<_>>();
+    let author = match author {
+        Some(a) => a.signature()?,
+        None => target.author().to_owned(),
+    };
+    let mut new_id = create_commit(
+        &repo,
+        false,
+        &author,
+        &sig,
+        &msg,
+        &target.tree().map_err(Error::GitE)?,
+        &parents.iter().collect::<Vec<_>>(),
+    )?;
+    for c in descendants.iter().rev() {
+        let parent = repo.find_commit(new_id).map_err(Error::GitE)?;
+        let tree = c.tree().map_err(Error::GitE)?;
+        new_id = create_commit(
+            &repo,
+            false,
+            &c.author(),
+            &sig,
+            c.message_raw().unwrap_or_default(),
+            &tree,
+            &[&parent],
+        )?;
+    }
 
-    Ok((
-        config.get_string("user.name").map_err(Error::GitE)?,
-        config.get_string("user.email").map_err(Error::GitE)?,
-    ))
+    move_head(&repo, new_id, &format!("ggw reword: {}", rev.as_ref()))
+}
+
+pub fn backup_index<P: AsRef<Path>>(path: P) -> Result<IndexBackup, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(Error::GitE)?
+        .id();
+    let tree = repo
+        .index()
+        .and_then(|mut i| i.write_tree())
+        .map_err(Error::GitE)?;
+    Ok(IndexBackup { head, tree })
+}
+
+/// move HEAD back and restore index. workdir is not touched.
+pub fn restore_index<P: AsRef<Path>>(path: P, backup: &IndexBackup) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head = repo.find_object(backup.head, None).map_err(Error::GitE)?;
+    repo.reset(&head, ResetType::Soft, None)
+        .map_err(Error::GitE)?;
+
+    let tree = repo.find_tree(backup.tree).map_err(Error::GitE)?;
+    let mut index = repo.index().map_err(Error::GitE)?;
+    index.read_tree(&tree).map_err(Error::GitE)?;
+    index.write().map_err(Error::GitE)
+}
+
+/// unstage everything. (`git reset` without paths)
+pub fn reset_index_to_head<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let head_tree = repo
+        .head()
+        .and_then(|h| h.peel_to_tree())
+        .map_err(Error::GitE)?;
+    let mut index = repo.index().map_err(Error::GitE)?;
+    index.read_tree(&head_tree).map_err(Error::GitE)?;
+    index.write().map_err(Error::GitE)
+}
+
+/// stage again what was staged at `backup`, except `committed` hunks. use after `split`.
+/// returns files whose staged change doesn't apply on the new HEAD. they are left unstaged.
+pub fn restage<P: AsRef<Path>>(
+    path: P,
+    backup: &IndexBackup,
+    committed: &[&Hunk],
+) -> Result<Vec<String>, Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let old = repo
+        .find_commit(backup.head)
+        .and_then(|c| c.tree())
+        .map_err(Error::GitE)?;
+    let staged = repo.find_tree(backup.tree).map_err(Error::GitE)?;
+    let diff = repo
+        .diff_tree_to_tree(
+            Some(&old),
+            Some(&staged),
+            Some(DiffOptions::new().show_binary(true)),
+        )
+        .map_err(Error::GitE)?;
+    let paths = diff_paths(&diff);
+
+    let mut failed = Vec::new();
+    for (d, delta) in collect_hunks(&diff)?.iter().enumerate() {
+        let picks = delta
+            .iter()
+            .map(|h| !committed.iter().any(|c| c.key() == h.key()))
+            .collect::<Vec<_>>();
+        // binary files have no hunks
+        if !picks.is_empty() && !picks.contains(&true) {
+            continue;
+        }
+        // one delta at a time. a conflict leaves only that file unstaged.
+        let delta_idx = Cell::new(0usize);
+        let hunk_idx = Cell::new(0usize);
+        let mut opts = ApplyOptions::new();
+        opts.delta_callback(|_| {
+            let i = delta_idx.get();
+            delta_idx.set(i + 1);
+            i == d
+        });
+        opts.hunk_callback(|_| {
+            let h = hunk_idx.get();
+            hunk_idx.set(h + 1);
+            picks.get(h).copied().unwrap_or(false)
+        });
+        if repo
+            .apply(&diff, ApplyLocation::Index, Some(&mut opts))
+            .is_err()
+        {
+            failed.push(paths[d].clone());
+        }
+    }
+    Ok(failed)
+}
+
+/// stage only `hunks` to index and commit them on HEAD.
+pub fn commit_hunks<P: AsRef<Path>, M: AsRef<str>>(
+    path: P,
+    hunks: &[&Hunk],
+    msg: &M,
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let repo = Repository::open(path).map_err(Error::GitE)?;
+    let index = repo.index().map_err(Error::GitE)?;
+    let diff = repo
+        .diff_index_to_workdir(Some(&index), Some(&mut hunk_diff_opts()))
+        .map_err(Error::GitE)?;
+
+    let picks = collect_hunks(&diff)?
+        .iter()
+        .map(|d| {
+            d.iter()
+                .map(|h| hunks.iter().any(|p| p.key() == h.key()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // untracked files can't be applied to index. add them directly.
+    let untracked = diff
+        .deltas()
+        .map(|d| d.status() == Delta::Untracked)
+        .collect::<Vec<_>>();
+
+    // apply calls delta_cb then hunk_cb for each hunk of the delta, in diff order.
+    let delta_idx = Cell::new(0usize);
+    let hunk_idx = Cell::new(0usize);
+    let mut opts = ApplyOptions::new();
+    opts.delta_callback(|_| {
+        let d = delta_idx.get();
+        delta_idx.set(d + 1);
+        hunk_idx.set(0);
+        // skip files without picked hunks. otherwise an empty file could be added.
+        !untracked[d] && picks.get(d).is_some_and(|p| p.contains(&true))
+    });
+    opts.hunk_callback(|_| {
+        let h = hunk_idx.get();
+        hunk_idx.set(h + 1);
+        picks
+            .get(delta_idx.get() - 1)
+            .and_then(|p| p.get(h))
+            .copied()
+            .unwrap_or(false)
+    });
+    repo.apply(&diff, ApplyLocation::Index, Some(&mut opts))
+        .map_err(Error::GitE)?;
+
+    let mut index = repo.index().map_err(Error::GitE)?;
+    index.read(true).map_err(Error::GitE)?;
+    for (d, delta) in diff.deltas().enumerate() {
+        if let Some(p) = delta
+            .new_file()
+            .path()
+            .filter(|_| untracked[d] && picks[d].contains(&true))
+        {
+            index.add_path(p).map_err(Error::GitE)?;
+        }
+    }
+    index.write().map_err(Error::GitE)?;
+    if verify {
+        hook::run_hook::<&str>(&repo, "pre-commit", &[])?;
+    }
+    let tree = index_tree(&repo)?;
+    let parent = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(Error::GitE)?;
+    commit_on_head(
+        &repo,
+        msg.as_ref(),
+        author,
+        committer,
+        verify,
+        &tree,
+        &[&parent],
+    )
+}
+
+/// `GIT_<kind>_NAME`/`GIT_<kind>_EMAIL` env, then `user.name`/`user.email` of repo config.
+/// (local, global, system and includes) `kind` is `AUTHOR` or `COMMITTER`.
+pub fn get_ident<P: AsRef<Path>>(path: P, kind: &str) -> Result<Ident, Error> {
+    let config = Repository::open(path)
+        .and_then(|r| r.config())
+        .map_err(Error::GitE)?;
+
+    let get = |env_key: String, config_key: &str| {
+        env::var(env_key)
+            .ok()
+            .or_else(|| config.get_string(config_key).ok())
+            .filter(|v| !v.trim().is_empty())
+    };
+    match (
+        get(format!("GIT_{kind}_NAME"), "user.name"),
+        get(format!("GIT_{kind}_EMAIL"), "user.email"),
+    ) {
+        (Some(name), Some(email)) => Ok(Ident { name, email }),
+        _ => Err(Error::NotFoundIdent(kind.to_lowercase())),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use std::{collections::HashMap, env, fs, path::Path};
+
+    use git2::{Repository, Signature};
 
-    use crate::git::{self, get_user_email};
+    use crate::git::{self, Ident, get_ident};
 
     #[test]
     fn test_git_commit() {
         let path = env::current_dir().unwrap();
-        let sig = get_user_email().unwrap();
-        let res = git::git_commit(path, &"test", sig.0, sig.1);
+        let sig = get_ident(&path, "AUTHOR").unwrap();
+        let res = git::git_commit(path, &"test", &sig, &sig, true);
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_blame_staged_lines() {
+        let dir = env::temp_dir().join(format!("ggw_blame_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let sig = Signature::now("a", "a@example.com").unwrap();
+        let commit = |text: &str, msg: &str| {
+            fs::write(dir.join("a.txt"), text).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("a.txt")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            repo.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                msg,
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+        let lines = (1..=10).map(|i| format!("{i}\n")).collect::<Vec<_>>();
+        let first = commit(&lines.concat(), "first");
+        let mut lines = lines;
+        lines[3] = "four\n".to_string();
+        commit(&lines.concat(), "second");
+        // line 6 only. line 4 of "second" is within 3 lines of context
+        lines[5] = "six\n".to_string();
+        fs::write(dir.join("a.txt"), lines.concat()).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        // not staged
+        lines[0] = "one\n".to_string();
+        fs::write(dir.join("a.txt"), lines.concat()).unwrap();
+
+        let counts = git::blame_staged_lines(&dir).unwrap();
+        assert_eq!(counts, HashMap::from([(first.to_string(), 1)]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pre_commit_restage() {
+        let dir = env::temp_dir().join(format!("ggw_hook_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+        // a formatter which fixes and re-stages files
+        let hook = dir.join(".git/hooks/pre-commit");
+        fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        fs::write(&hook, "#!/bin/sh\necho fixed > a.txt && git add a.txt\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let ident = Ident::parse("a <a@example.com>").unwrap();
+        git::git_commit(&dir, &"test", &ident, &ident, true).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let blob = tree
+            .get_path(Path::new("a.txt"))
+            .unwrap()
+            .to_object(&repo)
+            .unwrap()
+            .peel_to_blob()
+            .unwrap();
+        assert_eq!(blob.content(), b"fixed\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_ident() {
+        let i = Ident::parse("Foo Bar <foo@example.com>").unwrap();
+        assert_eq!(i.name, "Foo Bar");
+        assert_eq!(i.email, "foo@example.com");
+        assert_eq!(i.to_string(), "Foo Bar <foo@example.com>");
+        assert!(Ident::parse("foo@example.com").is_none());
+        assert!(Ident::parse("<foo@example.com>").is_none());
+    }
 }
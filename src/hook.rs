@@ -0,0 +1,45 @@
+use std::{ffi::OsStr, path::Path, process::Command};
+
+use git2::Repository;
+
+use crate::Error;
+
+/// run `<hooks dir>/<name>` if it exists and is executable. same as git.
+/// hooks dir is `core.hooksPath` or `.git/hooks`.
+pub fn run_hook<S: AsRef<OsStr>>(repo: &Repository, name: &str, args: &[S]) -> Result<(), Error> {
+    let workdir = repo.workdir().unwrap_or(repo.path());
+    let hooks_dir = repo
+        .config()
+        .and_then(|c| c.get_path("core.hooksPath"))
+        .map(|p| workdir.join(p))
+        .unwrap_or_else(|_| repo.path().join("hooks"));
+
+    let hook = hooks_dir.join(name);
+    if !is_executable(&hook) {
+        return Ok(());
+    }
+
+    let status = Command::new(&hook)
+        .args(args)
+        .current_dir(workdir)
+        .status()
+        .map_err(Error::IoE)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Hook(name.to_string()))
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,100 @@
+use regex::Regex;
+
+use crate::{Error, config::IssueRule};
+
+/// used when `issue_rules` is not set in config.
+/// `feature/PROJ-1234-add-login` -> `Refs: PROJ-1234`, `fix/567-crash` -> `Refs: #567`
+const DEFAULT_RULES: [(&str, &str); 2] = [
+    (r"([A-Z][A-Z0-9]+-\d+)", "Refs: {id}"),
+    (r"(?:^|/)(\d+)(?:-|$)", "Refs: #{id}"),
+];
+
+pub struct IssueRef {
+    pub id: String,
+    pub footer: String,
+}
+
+/// extract ticket ids from branch name. `{id}` in footer is replaced with
+/// the first capture group, or the whole match without groups.
+pub fn extract_refs<T: AsRef<str>>(
+    branch: T,
+    rules: Option<&Vec<IssueRule>>,
+) -> Result<Vec<IssueRef>, Error> {
+    let rules = match rules {
+        Some(r) => r.clone(),
+        None => DEFAULT_RULES
+            .iter()
+            .map(|(p, f)| IssueRule {
+                pattern: p.to_string(),
+                footer: f.to_string(),
+            })
+            .collect(),
+    };
+
+    let mut refs: Vec<IssueRef> = Vec::new();
+    for rule in rules {
+        let re = Regex::new(&rule.pattern).map_err(Error::Regex)?;
+        for cap in re.captures_iter(branch.as_ref()) {
+            let Some(id) = cap.get(1).or_else(|| cap.get(0)) else {
+                continue;
+            };
+            // an id matched by multiple rules uses the first one
+            if refs.iter().any(|r| r.id == id.as_str()) {
+                continue;
+            }
+            refs.push(IssueRef {
+                id: id.as_str().to_string(),
+                footer: rule.footer.replace("{id}", id.as_str()),
+            });
+        }
+    }
+    Ok(refs)
+}
+
+/// a line for prompt. footers are added by ggw, so llm shouldn't write them.
+pub fn issue_hint<T: AsRef<str>>(branch: T, refs: &[IssueRef]) -> Option<String> {
+    if refs.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "The current branch is \"{}\" and the change relates to: {}. Footers referencing them are added automatically, so do not include them.",
+        branch.as_ref(),
+        refs.iter()
+            .map(|r| r.id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_refs;
+    use crate::config::IssueRule;
+
+    #[test]
+    fn test_default_rules() {
+        let refs = extract_refs("feature/PROJ-1234-add-login", None).unwrap();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].footer, "Refs: PROJ-1234");
+
+        let refs = extract_refs("fix/567-crash", None).unwrap();
+        assert_eq!(refs[0].footer, "Refs: #567");
+
+        assert!(extract_refs("main", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = vec![IssueRule {
+            pattern: r"^fix/(\d+)".to_string(),
+            footer: "Closes #{id}".to_string(),
+        }];
+        let refs = extract_refs("fix/567-crash", Some(&rules)).unwrap();
+        assert_eq!(refs[0].footer, "Closes #567");
+        assert!(
+            extract_refs("feature/567-foo", Some(&rules))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
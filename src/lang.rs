@@ -0,0 +1,38 @@
+/// instruction for the output language. english needs nothing.
+/// for commit msgs, Conventional Commits keywords are kept in english.
+pub fn lang_hint<T: AsRef<str>>(lang: Option<T>, commit_msg: bool) -> Option<String> {
+    let lang = lang?;
+    let lang = lang.as_ref().trim();
+    if lang.is_empty() || is_english(lang) {
+        return None;
+    }
+    Some(if commit_msg {
+        format!(
+            "Write the description and body in {lang}. Keep the type (feat, fix, docs, ...), the scope and footer tokens in English."
+        )
+    } else {
+        format!("Respond in {lang}, regardless of the language of the instructions or the input.")
+    })
+}
+
+fn is_english(lang: &str) -> bool {
+    let lang = lang.to_lowercase();
+    lang == "english" || lang == "en" || lang.starts_with("en-") || lang.starts_with("en_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lang_hint;
+
+    #[test]
+    fn test_lang_hint() {
+        assert!(lang_hint(Some("ja"), true).unwrap().contains("in ja."));
+        assert!(
+            lang_hint(Some("Japanese"), false)
+                .unwrap()
+                .starts_with("Respond in Japanese")
+        );
+        assert_eq!(lang_hint(Some("en-US"), true), None);
+        assert_eq!(lang_hint(None::<&str>, true), None);
+    }
+}
@@ -1,15 +1,33 @@
-use llm_api_rs::{
-    Anthropic, ChatCompletionRequest, ChatMessage, Gemini, LlmApiError, LlmProvider, OpenAI,
+use llm_api_rs::{ChatCompletionRequest, ChatMessage, LlmApiError, LlmProvider, OpenAI};
+use ollama_rs::{
+    Ollama,
+    generation::chat::{ChatMessage as OllamaMessage, MessageRole, request::ChatMessageRequest},
 };
-use ollama_rs::{Ollama, generation::completion::request::GenerationRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use tokio::runtime::Runtime;
 
-use crate::cli_helper;
+use crate::{cli_helper, policy, redact};
+
+// anthropic, gemini and deepseek are called directly. `ChatCompletionRequest` of llm-api-rs
+// has no system field, and its clients send a `system` role message, which anthropic and
+// gemini don't take as the system prompt. its `OpenAI` client only talks to openai's host.
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// `max_tokens` is required by anthropic.
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+const GEMINI_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const DEEPSEEK_URL: &str = "https://api.deepseek.com/chat/completions";
+/// providers running on this machine. requests to others are redacted.
+pub const LOCAL_PROVIDERS: [&str; 1] = ["ollama"];
 
 #[derive(Debug)]
 pub enum LlmError {
     Ollama(ollama_rs::error::OllamaError),
     Other(LlmApiError),
+    Http(reqwest::Error),
+    Api(String),
+    Refused(String),
     UndefinedProvider,
     NotFoundAPIKey,
 }
@@ -19,22 +37,70 @@ impl std::fmt::Display for LlmError {
         match self {
             LlmError::Ollama(e) => write!(f, "Ollama error: {e}"),
             LlmError::Other(e) => write!(f, "Other error: {e}"),
+            LlmError::Http(e) => write!(f, "Http error: {e}"),
+            LlmError::Api(e) => write!(f, "API error: {e}"),
+            LlmError::Refused(e) => write!(f, "refused to send: {e}"),
             LlmError::UndefinedProvider => write!(f, "Undefined LLM provider"),
             LlmError::NotFoundAPIKey => write!(f, "API key not found"),
         }
     }
 }
 
+/// a message of conversation. `role` is `user` or `assistant`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    pub fn user<T: AsRef<str>>(content: T) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.as_ref().to_string(),
+        }
+    }
+
+    pub fn assistant<T: AsRef<str>>(content: T) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.as_ref().to_string(),
+        }
+    }
+}
+
+/// single-turn. `system` is sent as the provider's system prompt, and `pmt` as a user message.
 pub fn call_llm<T: AsRef<str>>(
+    system: Option<T>,
     pmt: T,
     provider: T,
     model: T,
     api_key: Option<String>,
     temperature: Option<f32>,
     max_tokens: Option<u32>,
+) -> Result<String, LlmError> {
+    chat_llm(
+        system.map(|s| s.as_ref().to_string()),
+        vec![Message::user(pmt)],
+        provider,
+        model,
+        api_key,
+        temperature,
+        max_tokens,
+    )
+}
+
+/// multi-turn. `messages` is the history ending with a user message.
+pub fn chat_llm<T: AsRef<str>>(
+    system: Option<String>,
+    messages: Vec<Message>,
+    provider: T,
+    model: T,
+    api_key: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
 ) -> Result<String, LlmError> {
     let model = model.as_ref().to_string();
-    let pmt = pmt.as_ref().to_string();
     // let rt = Runtime::new().unwrap();
 
     let api_key = match api_key {
@@ -47,104 +113,179 @@ pub fn call_llm<T: AsRef<str>>(
             }
         }
     };
+    policy::check(&provider)?;
+    let (system, messages) = if is_local(&provider) {
+        (system, messages)
+    } else {
+        redact::redact_request(provider.as_ref(), system, messages)?
+    };
     match provider.as_ref().to_lowercase().as_str() {
-        "ollama" => cli_helper::a(|| ollama(pmt, model)),
-        "anthropic" => {
-            cli_helper::a(move || anthropic(api_key, model, pmt, temperature, max_tokens))
+        "ollama" => cli_helper::a(|| ollama(system, messages, model)),
+        "anthropic" => cli_helper::a(move || {
+            anthropic(api_key, model, system, messages, temperature, max_tokens)
+        }),
+        "deepseek" => cli_helper::a(move || {
+            deep_seek(api_key, model, system, messages, temperature, max_tokens)
+        }),
+        "gemini" => {
+            cli_helper::a(move || gemini(api_key, model, system, messages, temperature, max_tokens))
         }
-        "deepseek" => {
-            cli_helper::a(move || deep_seek(api_key, model, pmt, temperature, max_tokens))
+        "openai" => {
+            cli_helper::a(move || openai(api_key, model, system, messages, temperature, max_tokens))
         }
-        "gemini" => cli_helper::a(move || gemini(api_key, model, pmt, temperature, max_tokens)),
-        "openai" => cli_helper::a(move || openai(api_key, model, pmt, temperature, max_tokens)),
         _ => Err(LlmError::UndefinedProvider),
     }
 }
 
-async fn ollama(pmt: String, model: String) -> Result<String, LlmError> {
+pub fn is_local<T: AsRef<str>>(provider: T) -> bool {
+    LOCAL_PROVIDERS.contains(&provider.as_ref().to_lowercase().as_str())
+}
+
+/// openai compatible. system prompt is the first message of `system` role.
+fn chat_messages(system: Option<String>, messages: Vec<Message>) -> Vec<ChatMessage> {
+    system
+        .map(|s| ChatMessage {
+            role: "system".to_string(),
+            content: s,
+        })
+        .into_iter()
+        .chain(messages.into_iter().map(|m| ChatMessage {
+            role: m.role,
+            content: m.content,
+        }))
+        .collect()
+}
+
+async fn ollama(
+    system: Option<String>,
+    messages: Vec<Message>,
+    model: String,
+) -> Result<String, LlmError> {
     let ollama = Ollama::default();
 
-    let res = ollama.generate(GenerationRequest::new(model, pmt)).await;
+    let messages = system
+        .map(OllamaMessage::system)
+        .into_iter()
+        .chain(messages.into_iter().map(|m| {
+            let role = match m.role.as_str() {
+                "assistant" => MessageRole::Assistant,
+                _ => MessageRole::User,
+            };
+            OllamaMessage::new(role, m.content)
+        }))
+        .collect();
+    let res = ollama
+        .send_chat_messages(ChatMessageRequest::new(model, messages))
+        .await;
     match res {
-        Ok(v) => Ok(v.response.to_string()),
+        Ok(v) => Ok(v.message.content),
         Err(e) => Err(LlmError::Ollama(e)),
     }
 }
 
+/// messages api. system prompt is the top-level `system` field.
 async fn anthropic<T: AsRef<str>>(
     api_key: T,
     model: String,
-    pmt: String,
+    system: Option<String>,
+    messages: Vec<Message>,
     tmp: Option<f32>,
     max_tokens: Option<u32>,
 ) -> Result<String, LlmError> {
-    let client = Anthropic::new(api_key.as_ref().to_string());
+    let mut body = json!({
+        "model": model,
+        "max_tokens": max_tokens.unwrap_or(ANTHROPIC_MAX_TOKENS),
+        "messages": messages,
+    });
+    if let Some(s) = system {
+        body["system"] = json!(s);
+    }
+    if let Some(t) = tmp {
+        body["temperature"] = json!(t);
+    }
+    let req = reqwest::Client::new()
+        .post(ANTHROPIC_URL)
+        .header("x-api-key", api_key.as_ref())
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&body);
+    let res = send_json(req).await?;
 
-    let req = ChatCompletionRequest {
-        model,
-        messages: vec![ChatMessage {
-            role: "user".to_string(),
-            content: pmt,
-        }],
-        temperature: tmp,
-        max_tokens,
-    };
-    client
-        .chat_completion(req)
-        .await
-        .map(|f| {
-            f.choices
-                .iter()
-                .map(|f| f.message.content.to_string())
-                .collect::<String>()
-        })
-        .map_err(LlmError::Other)
+    Ok(res["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c["text"].as_str())
+        .collect::<String>())
 }
 
+/// generateContent api. system prompt is `systemInstruction`.
 async fn gemini<T: AsRef<str>>(
     api_key: T,
     model: String,
-    pmt: String,
+    system: Option<String>,
+    messages: Vec<Message>,
     tmp: Option<f32>,
     max_tokens: Option<u32>,
 ) -> Result<String, LlmError> {
-    let client = Gemini::new(api_key.as_ref().to_string());
-
-    let req = ChatCompletionRequest {
-        model,
-        messages: vec![ChatMessage {
-            role: "user".to_string(),
-            content: pmt,
-        }],
-        temperature: tmp,
-        max_tokens,
-    };
-    client
-        .chat_completion(req)
-        .await
-        .map(|f| {
-            f.choices
-                .iter()
-                .map(|f| f.message.content.to_string())
-                .collect::<String>()
+    let contents = messages
+        .into_iter()
+        .map(|m| {
+            // gemini calls assistant `model`
+            let role = if m.role == "assistant" {
+                "model"
+            } else {
+                "user"
+            };
+            json!({ "role": role, "parts": [{ "text": m.content }] })
         })
-        .map_err(LlmError::Other)
+        .collect::<Vec<_>>();
+    let mut body = json!({ "contents": contents, "generationConfig": {} });
+    if let Some(t) = tmp {
+        body["generationConfig"]["temperature"] = json!(t);
+    }
+    if let Some(m) = max_tokens {
+        body["generationConfig"]["maxOutputTokens"] = json!(m);
+    }
+    if let Some(s) = system {
+        body["systemInstruction"] = json!({ "parts": [{ "text": s }] });
+    }
+    let req = reqwest::Client::new()
+        .post(format!("{GEMINI_URL}/{model}:generateContent"))
+        .header("x-goog-api-key", api_key.as_ref())
+        .json(&body);
+    let res = send_json(req).await?;
+
+    Ok(res["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p["text"].as_str())
+        .collect::<String>())
+}
+
+/// error response is returned as `LlmError::Api` with its body.
+async fn send_json(req: reqwest::RequestBuilder) -> Result<Value, LlmError> {
+    let res = req.send().await.map_err(LlmError::Http)?;
+    let status = res.status();
+    let body = res.text().await.map_err(LlmError::Http)?;
+    if !status.is_success() {
+        return Err(LlmError::Api(format!("{status}: {body}")));
+    }
+    serde_json::from_str(&body).map_err(|e| LlmError::Api(e.to_string()))
 }
 
 async fn openai<T: AsRef<str>>(
     api_key: T,
     model: String,
-    pmt: String,
+    system: Option<String>,
+    messages: Vec<Message>,
     tmp: Option<f32>,
     max_tokens: Option<u32>,
 ) -> Result<String, LlmError> {
     let client = OpenAI::new(api_key.as_ref().to_string());
     let req = ChatCompletionRequest {
         model,
-        messages: vec![ChatMessage {
-            role: "user".to_string(),
-            content: pmt,
-        }],
+        messages: chat_messages(system, messages),
         temperature: tmp,
         max_tokens,
     };
@@ -160,44 +301,54 @@ async fn openai<T: AsRef<str>>(
         .map_err(LlmError::Other)
 }
 
+/// openai compatible api on deepseek's host. system prompt is the first message of `system` role.
 async fn deep_seek<T: AsRef<str>>(
     api_key: T,
     model: String,
-    pmt: String,
+    system: Option<String>,
+    messages: Vec<Message>,
     tmp: Option<f32>,
     max_tokens: Option<u32>,
 ) -> Result<String, LlmError> {
-    let client = OpenAI::new(api_key.as_ref().to_string());
-    let req = ChatCompletionRequest {
-        model,
-        messages: vec![ChatMessage {
-            role: "user".to_string(),
-            content: pmt,
-        }],
-        temperature: tmp,
-        max_tokens,
-    };
-    client
-        .chat_completion(req)
-        .await
-        .map(|f| {
-            f.choices
-                .iter()
-                .map(|f| f.message.content.to_string())
-                .collect::<String>()
+    let messages = system
+        .map(|s| Message {
+            role: "system".to_string(),
+            content: s,
         })
-        .map_err(LlmError::Other)
+        .into_iter()
+        .chain(messages)
+        .collect::<Vec<_>>();
+    let mut body = json!({ "model": model, "messages": messages });
+    if let Some(t) = tmp {
+        body["temperature"] = json!(t);
+    }
+    if let Some(m) = max_tokens {
+        body["max_tokens"] = json!(m);
+    }
+    let req = reqwest::Client::new()
+        .post(DEEPSEEK_URL)
+        .bearer_auth(api_key.as_ref())
+        .json(&body);
+    let res = send_json(req).await?;
+
+    Ok(res["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c["message"]["content"].as_str())
+        .collect::<String>())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::llm::{call_llm, gemini};
+    use crate::llm::{Message, call_llm, gemini};
     use std::env;
     use tokio::runtime::Runtime;
 
     #[test]
     fn call_test() {
         let res = call_llm(
+            None,
             "hello",
             "gemini",
             "gemini-2.0-flash",
@@ -217,7 +368,8 @@ mod tests {
         let result = rt.block_on(gemini(
             api.unwrap(),
             "gemini-2.0-flash".to_string(),
-            "hello".to_string(),
+            None,
+            vec![Message::user("hello")],
             None,
             None,
         ));
@@ -1,39 +1,57 @@
+mod auth;
+mod chat;
+mod cli_doc;
 mod cli_helper;
 mod cmt_msg;
 mod config;
+mod conventional;
 mod custom_prompt;
+mod digest;
+mod doc;
+mod editor;
+mod fixup;
 mod git;
+mod guard;
+mod hook;
+mod issue;
+mod lang;
 mod llm;
+mod markdown;
+mod policy;
 mod read_codes;
 mod readme;
+mod redact;
+mod scan;
+mod scope;
+mod sign;
+mod split;
 mod storage;
+mod style;
 mod sum;
+mod template;
+mod trailer;
 
-use chrono::Local;
-use clap::{Parser, Subcommand};
-use config::Model;
+use clap::{CommandFactory, Parser, Subcommand};
+use config::{Config, Model};
 use custom_prompt::custom_prpmt;
-use dialoguer::Input;
+use dialoguer::{Input, MultiSelect};
 use get_input::yes_no;
+use git::Ident;
 use std::{
     env::{self},
     fmt::Display,
-    fs, io,
+    fs,
+    io::{self, IsTerminal},
     path::{Path, PathBuf},
 };
 use storage::Storage;
 use sum::summarize_diff;
-
-const ANTHROPIC_API: &str = "GGW_ANTHROPIC_API";
-const GEMINI_API: &str = "GGW_GEMINI_API";
-const OPENAI_API: &str = "GGW_OPENAI_API";
-const DEEPSEEK: &str = "GGW_DEEPSEEK_API";
+use template::{Task, Template};
 
 #[derive(Debug)]
 pub enum Error {
     GitE(git2::Error),
     Llm(llm::LlmError),
-    EnvE(env::VarError),
     StrE(storage::Error),
     FailedParseCli,
     IoE(io::Error),
@@ -42,6 +60,25 @@ pub enum Error {
     NotSettingPath,
     NotFoundHome,
     NotFoundConfig(String),
+    EmptyCmtMsg,
+    Editor(String),
+    CannotRewrite(String),
+    NotFoundFixupTarget,
+    InvalidSplitPlan(String),
+    Hook(String),
+    Sign(String),
+    NotFoundIdent(String),
+    InvalidIdent(String),
+    NotFoundTeam,
+    Regex(regex::Error),
+    InvalidManifest(String),
+    NotFoundPreset(String),
+    UnsafeOutput(String),
+    NotFoundApiKey(String),
+    KeyCommand(String),
+    NotFoundSection(String),
+    ExistsFile(String),
+    InvalidSessionName(String),
 }
 
 impl Display for Error {
@@ -49,7 +86,6 @@ impl Display for Error {
         match self {
             Error::GitE(e) => write!(f, "git error: {e}"),
             Error::Llm(e) => write!(f, "llm error: {e}"),
-            Error::EnvE(e) => write!(f, "enviroment var error: {e}"),
             Error::FailedParseCli => write!(f, "failed parse cli"),
             Error::IoE(e) => write!(f, "io error: {e}"),
             Error::NotFoundFile => write!(f, "not found file"),
@@ -58,6 +94,47 @@ impl Display for Error {
             Error::NotFoundConfig(p) => write!(f, "not found config at {p}"),
             Error::NotFoundHome => write!(f, "not found home dir in your machine"),
             Error::StrE(error) => write!(f, "storage error: {error}"),
+            Error::EmptyCmtMsg => write!(f, "aborting commit due to empty commit message"),
+            Error::Editor(e) => write!(f, "there was a problem with the editor '{e}'"),
+            Error::CannotRewrite(e) => write!(f, "cannot rewrite history: {e}"),
+            Error::NotFoundFixupTarget => write!(f, "not found a commit to fixup"),
+            Error::InvalidSplitPlan(e) => write!(f, "invalid split plan: {e}"),
+            Error::Hook(h) => write!(f, "{h} hook failed"),
+            Error::Sign(e) => write!(f, "failed to sign commit: {e}"),
+            Error::NotFoundIdent(k) => write!(
+                f,
+                "{k} identity unknown. set user.name and user.email by `git config`, or use --author \"Name <email>\""
+            ),
+            Error::InvalidIdent(i) => write!(
+                f,
+                "invalid identity '{i}'. should be \"Name <email>\" or an alias of team in config"
+            ),
+            Error::NotFoundTeam => write!(f, "team is not set in config"),
+            Error::Regex(e) => write!(f, "regex error: {e}"),
+            Error::InvalidManifest(e) => write!(f, "invalid manifest {e}"),
+            Error::UnsafeOutput(e) => write!(f, "rejected llm output: {e}"),
+            Error::NotFoundApiKey(p) => write!(
+                f,
+                "api key of {p} not found. set {}, `key_command.{p}` in config, or run `ggw auth set {p}`",
+                auth::env_name(p).unwrap_or_default()
+            ),
+            Error::KeyCommand(e) => write!(f, "key command failed: {e}"),
+            Error::NotFoundSection(s) => write!(
+                f,
+                "not found '{s}'. it should be a heading or `<!-- ggw:begin {s} -->` ... `<!-- ggw:end -->` in the document"
+            ),
+            Error::ExistsFile(p) => write!(
+                f,
+                "{p} already exists. use -m to merge into it or -o to over write it"
+            ),
+            Error::InvalidSessionName(n) => write!(
+                f,
+                "invalid session name '{n}'. it can't contain '/', '\\' or '..'"
+            ),
+            Error::NotFoundPreset(p) => write!(
+                f,
+                "not found preset '{p}' in config. see `ggw cst --list`, or use --raw to send it as a prompt"
+            ),
         }
     }
 }
@@ -69,7 +146,7 @@ impl Display for Error {
     about = "this cli create a git commit msg by llm"
 )]
 struct Cli {
-    #[arg(short = 'y', long = "yes")]
+    #[arg(short = 'y', long = "yes", help = "don't confirm")]
     yes: bool,
 
     // #[arg(short = 's', long = "service")]
@@ -83,6 +160,35 @@ struct Cli {
     #[arg(short = 'p', long = "path", help = "work path")]
     path: Option<String>,
 
+    #[arg(
+        long = "no-verify",
+        global = true,
+        help = "skip pre-commit and commit-msg hooks"
+    )]
+    no_verify: bool,
+
+    #[arg(
+        long = "author",
+        global = true,
+        help = "override commit author. --author \"Name <email>\""
+    )]
+    author: Option<String>,
+
+    #[arg(
+        long = "co-author",
+        global = true,
+        num_args = 0..,
+        help = "add Co-authored-by trailers. alias of team in config or \"Name <email>\". select from team without value"
+    )]
+    co_author: Option<Vec<String>>,
+
+    #[arg(
+        long = "lang",
+        global = true,
+        help = "language of generated text. e.g. ja, Japanese. (default: ggw.lang in git config, lang in config)"
+    )]
+    lang: Option<String>,
+
     #[command(subcommand)]
     subcommand: Commands,
 }
@@ -99,7 +205,38 @@ enum Commands {
     Sum(Sum),
 
     #[command(name = "cst", about = "use custom prompt")]
-    Cst(Cst), // Chat(Chat),
+    Cst(Cst),
+
+    #[command(name = "chat", about = "chat about the repository")]
+    Chat(Chat),
+
+    #[command(
+        name = "reword",
+        about = "regenerate msg of an older commit from its diff"
+    )]
+    Reword(Reword),
+
+    #[command(
+        name = "fixup",
+        about = "find the commit staged changes belong to and fixup"
+    )]
+    Fixup(Fixup),
+
+    #[command(name = "split", about = "split changes into multiple logical commits")]
+    Split(Split),
+
+    #[command(name = "prompt", about = "show prompt templates")]
+    Prompt(PromptCmd),
+
+    #[command(name = "auth", about = "manage api keys")]
+    Auth(AuthCmd),
+
+    #[command(
+        name = "docs",
+        visible_alias = "doc",
+        about = "generate docs. `cli` needs no llm"
+    )]
+    Docs(DocsCmd),
 }
 
 #[derive(Debug, clap::Args, Clone)]
@@ -109,6 +246,29 @@ struct Commit {
 
     #[arg(short = 'a', long = "cumstom-prompt", help = "add custom prompt")]
     a: bool,
+
+    #[arg(
+        short = 'e',
+        long = "editor",
+        help = "edit msg in $GIT_EDITOR/$EDITOR with diff stat"
+    )]
+    editor: bool,
+
+    #[arg(
+        long = "amend",
+        help = "regenerate msg from HEAD's parent and amend HEAD"
+    )]
+    amend: bool,
+
+    #[arg(
+        long = "history",
+        value_name = "N",
+        help = "use last N commit msgs as style examples. 0 disables"
+    )]
+    history: Option<usize>,
+
+    #[arg(long = "enforce-scopes", help = "use only scopes found in history")]
+    enforce_scopes: bool,
 }
 
 #[derive(Debug, clap::Args, Clone)]
@@ -116,6 +276,7 @@ struct Readme {
     #[arg(
         short = 's',
         long = "sources",
+        help = "files to read",
         conflicts_with = "dir",
         required_unless_present = "dir"
     )]
@@ -124,56 +285,300 @@ struct Readme {
     #[arg(
         short = 'd',
         long = "directory",
+        help = "read files under this directory",
         conflicts_with = "source_path_list",
         required_unless_present = "source_path_list"
     )]
     dir: Option<String>,
 
-    #[arg(short = 'm', long = "allow-merge")]
+    #[arg(
+        long = "include",
+        num_args = 1..,
+        requires = "dir",
+        help = "globs of files to read under --directory. e.g. 'src/**/*.rs'"
+    )]
+    include: Option<Vec<String>>,
+
+    #[arg(
+        long = "exclude",
+        num_args = 1..,
+        requires = "dir",
+        help = "globs of files to skip under --directory"
+    )]
+    exclude: Option<Vec<String>>,
+
+    #[arg(
+        long = "max-size",
+        default_value_t = scan::DEFAULT_MAX_SIZE,
+        help = "skip files larger than this (bytes)"
+    )]
+    max_size: u64,
+
+    #[arg(
+        long = "budget",
+        default_value_t = digest::DEFAULT_BUDGET,
+        help = "max tokens of the context. full file contents are added by priority within it"
+    )]
+    budget: usize,
+
+    #[arg(
+        long = "show-context",
+        help = "print the context for llm and exit without calling it"
+    )]
+    show_context: bool,
+
+    #[arg(
+        long = "section",
+        help = "regenerate only this heading or `<!-- ggw:begin <name> -->` block"
+    )]
+    section: Option<String>,
+
+    #[arg(
+        long = "doc",
+        requires = "section",
+        help = "markdown file which has the section (default README.md)"
+    )]
+    doc: Option<String>,
+
+    #[arg(
+        short = 'm',
+        long = "allow-merge",
+        help = "merge into the existing README by heading sections"
+    )]
     allow_merge: bool,
 
-    #[arg(short = 'o', long = "over-write")]
+    #[arg(
+        short = 'o',
+        long = "over-write",
+        help = "replace the existing file instead of merging"
+    )]
     allow_over_write: bool,
+
+    #[arg(
+        long = "output",
+        conflicts_with = "section",
+        help = "file to write (default README.generated.md, or README.md with -m)"
+    )]
+    output: Option<String>,
+
+    #[arg(
+        long = "stdout",
+        conflicts_with = "output",
+        help = "print the result instead of writing a file"
+    )]
+    stdout: bool,
 }
 
 #[derive(Debug, clap::Args, Clone)]
 struct Sum {}
 
-// #[derive(Debug, clap::Args, Clone)]
-// struct Chat {}
+#[derive(Debug, clap::Args, Clone)]
+struct Chat {
+    #[arg(long = "load", help = "continue a session saved by /save")]
+    load: Option<String>,
+}
 
 #[derive(Debug, clap::Args, Clone)]
 struct Cst {
-    preset: String,
+    #[arg(
+        required_unless_present = "list",
+        help = "name of prompt.custom_prompt in config"
+    )]
+    preset: Option<String>,
+
+    #[arg(
+        long = "raw",
+        help = "send preset as a prompt without looking up config"
+    )]
+    raw: bool,
+
+    #[arg(long = "list", help = "show presets in config")]
+    list: bool,
+
+    #[arg(long = "file", num_args = 1.., help = "add contents of files as context")]
+    file: Option<Vec<String>>,
+
+    #[arg(
+        long = "dir",
+        help = "add contents of files in the directory as context"
+    )]
+    dir: Option<String>,
+
+    #[arg(long = "diff", help = "add current git diff as context")]
+    diff: bool,
+}
+
+#[derive(Debug, clap::Args, Clone)]
+struct Reword {
+    #[arg(help = "commit to reword. e.g. HEAD~2, a1b2c3d")]
+    rev: String,
+}
+
+#[derive(Debug, clap::Args, Clone)]
+struct Fixup {}
+
+#[derive(Debug, clap::Args, Clone)]
+struct Split {}
+
+#[derive(Debug, clap::Args, Clone)]
+struct PromptCmd {
+    #[command(subcommand)]
+    action: PromptAction,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum PromptAction {
+    #[command(
+        name = "show",
+        about = "print the default template to copy to ~/.ggw/prompts/<task>.md"
+    )]
+    Show {
+        task: Task,
+        #[arg(long = "current", help = "print the template in use instead")]
+        current: bool,
+    },
+}
+
+#[derive(Debug, clap::Args, Clone)]
+struct AuthCmd {
+    #[command(subcommand)]
+    action: AuthAction,
+}
+
+#[derive(Debug, clap::Args, Clone)]
+struct DocsCmd {
+    #[command(subcommand)]
+    action: DocsAction,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum DocsAction {
+    #[command(
+        name = "cli",
+        about = "command line reference from the cli definitions"
+    )]
+    Cli {
+        #[arg(long = "format", value_enum, default_value_t = cli_doc::DocFormat::Markdown)]
+        format: cli_doc::DocFormat,
+        #[arg(
+            long = "inject",
+            conflicts_with = "format",
+            help = "replace the managed block in this markdown file instead of printing"
+        )]
+        inject: Option<String>,
+        #[arg(
+            long = "block",
+            default_value = "cli",
+            requires = "inject",
+            help = "name of `<!-- ggw:begin <name> -->` block"
+        )]
+        block: String,
+    },
+    #[command(name = "contributing", about = "generate CONTRIBUTING.md")]
+    Contributing(DocGen),
+    #[command(
+        name = "architecture",
+        about = "generate ARCHITECTURE.md from modules and public api"
+    )]
+    Architecture(DocGen),
+    #[command(name = "module", about = "generate a doc of a module")]
+    Module {
+        #[arg(help = "source file of the module, relative to -p. e.g. src/scan.rs")]
+        path: String,
+        #[command(flatten)]
+        options: DocGen,
+    },
+    #[command(
+        name = "rustdoc",
+        about = "suggest doc comments for undocumented public items of a rust file"
+    )]
+    Rustdoc {
+        #[arg(help = "rust file, relative to -p")]
+        path: String,
+    },
+}
+
+#[derive(Debug, clap::Args, Clone)]
+struct DocGen {
+    #[arg(
+        long = "output",
+        help = "file to write (default CONTRIBUTING.md, ARCHITECTURE.md or docs/<module>.md)"
+    )]
+    output: Option<String>,
+
+    #[arg(
+        long = "budget",
+        default_value_t = digest::DEFAULT_BUDGET,
+        help = "max tokens of the context"
+    )]
+    budget: usize,
+
+    #[arg(
+        long = "show-context",
+        help = "print the context for llm and exit without calling it"
+    )]
+    show_context: bool,
+
+    #[arg(
+        short = 'm',
+        long = "allow-merge",
+        help = "merge into the existing file by heading sections"
+    )]
+    allow_merge: bool,
+
+    #[arg(short = 'o', long = "over-write", help = "replace the existing file")]
+    allow_over_write: bool,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum AuthAction {
+    #[command(
+        name = "set",
+        about = "store api key in ~/.ggw/credentials.json (mode 0600). read from stdin when piped"
+    )]
+    Set {
+        #[arg(value_parser = auth::PROVIDERS)]
+        provider: String,
+    },
+    #[command(name = "remove", about = "remove stored api key")]
+    Remove {
+        #[arg(value_parser = auth::PROVIDERS)]
+        provider: String,
+    },
+    #[command(
+        name = "list",
+        about = "show where api key of each provider comes from"
+    )]
+    List,
 }
 
 fn commit_from_gitdiff<T: AsRef<Path>, U: AsRef<str>>(
     project_path: &T,
     model: Model,
     api_key: Option<U>,
+    amend: bool,
+    template: Template,
     // options: (&Cli, &Commit),
     // ⚠️configとかにまとめるかも
     // 拡張性が低い
     // auto_commit: bool,
     // yes_option: bool,
 ) -> Result<String, Error> {
-    let git_diff = git::get_diff(project_path)?;
-    let commit_msg =
-        cmt_msg::create_cmt_msg(git_diff, model, api_key.map(|f| f.as_ref().to_string()))?;
+    let git_diff = if amend {
+        git::get_amend_diff(project_path)?
+    } else {
+        git::get_diff(project_path)?
+    };
+    let commit_msg = cmt_msg::create_cmt_msg(
+        git_diff,
+        template,
+        model,
+        api_key.map(|f| f.as_ref().to_string()),
+    )?;
 
     Ok(commit_msg)
 }
 
-fn resolve_api_key(model: &Model) -> Option<Result<String, env::VarError>> {
-    match model.provider.as_str() {
-        "anthropic" => Some(env::var(ANTHROPIC_API)),
-        "deepseek" => Some(env::var(DEEPSEEK)),
-        "gemini" => Some(env::var(GEMINI_API)),
-        "openai" => Some(env::var(OPENAI_API)),
-        _ => None,
-    }
-}
-
 fn resolve_work_path(cli: Cli) -> Result<PathBuf, Error> {
     let p = match cli.path {
         Some(p) => PathBuf::from(p),
@@ -199,11 +604,383 @@ fn resolve_config_path() -> Result<PathBuf, Error> {
     }
 }
 
+/// returns (`--author`, committer). committer falls back to `--author` when git has no ident.
+fn resolve_idents<P: AsRef<Path>>(cli: &Cli, pj_path: P) -> Result<(Option<Ident>, Ident), Error> {
+    let author = cli
+        .author
+        .as_ref()
+        .map(|a| Ident::parse(a).ok_or(Error::InvalidIdent(a.to_string())))
+        .transpose()?;
+
+    let committer = match (git::get_ident(&pj_path, "COMMITTER"), &author) {
+        (Ok(c), _) => c,
+        (Err(_), Some(a)) => a.clone(),
+        (Err(e), None) => return Err(e),
+    };
+    Ok((author, committer))
+}
+
+/// `Co-authored-by` trailers from `--co-author`.
+fn resolve_co_authors(cli: &Cli, config: &Config) -> Result<Vec<String>, Error> {
+    let Some(values) = &cli.co_author else {
+        return Ok(Vec::new());
+    };
+    let team = config.team().clone().unwrap_or_default();
+
+    let idents = if values.is_empty() {
+        if team.is_empty() {
+            return Err(Error::NotFoundTeam);
+        }
+        let mut members = team.values().cloned().collect::<Vec<_>>();
+        members.sort();
+        MultiSelect::new()
+            .with_prompt("select co-authors")
+            .items(&members)
+            .interact()
+            .unwrap()
+            .into_iter()
+            .map(|i| members[i].clone())
+            .collect::<Vec<_>>()
+    } else {
+        values
+            .iter()
+            .map(|v| team.get(v).cloned().unwrap_or(v.to_string()))
+            .collect()
+    };
+
+    idents
+        .into_iter()
+        .map(|i| match Ident::parse(&i) {
+            Some(ident) => Ok(format!("Co-authored-by: {ident}")),
+            None => Err(Error::InvalidIdent(i)),
+        })
+        .collect()
+}
+
+/// footers and a prompt hint from the ticket ids in current branch name.
+fn resolve_issue_refs<P: AsRef<Path>>(
+    pj_path: P,
+    config: &Config,
+) -> Result<(Vec<String>, Vec<String>), Error> {
+    let Some(branch) = git::current_branch(pj_path) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let refs = issue::extract_refs(&branch, config.issue_rules().as_ref())?;
+    Ok((
+        refs.iter().map(|r| r.footer.clone()).collect(),
+        issue::issue_hint(&branch, &refs).into_iter().collect(),
+    ))
+}
+
+/// commit style from history and whether to enforce its scopes. `samples` overrides config.
+fn resolve_style<P: AsRef<Path>>(
+    pj_path: P,
+    config: &Config,
+    samples: Option<usize>,
+    enforce_scopes: bool,
+) -> Result<Option<(style::Style, bool)>, Error> {
+    let history = config.history().clone().unwrap_or_default();
+    let samples = samples
+        .or(*history.samples())
+        .unwrap_or(style::DEFAULT_SAMPLES);
+    // no history in a new repo
+    if samples == 0 || git::head_id(&pj_path).is_err() {
+        return Ok(None);
+    }
+    let enforce = enforce_scopes || history.enforce_scopes().unwrap_or(false);
+    Ok(Some((style::learn_style(pj_path, samples)?, enforce)))
+}
+
+/// output language. `--lang` > `ggw.lang` in git config (per repo) > `lang` in config.
+fn resolve_lang<P: AsRef<Path>>(cli: &Cli, pj_path: P, config: &Config) -> Option<String> {
+    cli.lang
+        .clone()
+        .or_else(|| git::get_config(pj_path, "ggw.lang"))
+        .or_else(|| config.lang().clone())
+}
+
+/// template of task with common variables. the output language and data notice are added to `hints`.
+fn resolve_template<P: AsRef<Path>>(
+    task: Task,
+    cli: &Cli,
+    pj_path: P,
+    config: &Config,
+    mut hints: Vec<String>,
+) -> Result<Template, Error> {
+    let lang = resolve_lang(cli, &pj_path, config);
+    hints.push(guard::DATA_NOTICE.to_string());
+    match task {
+        Task::Cmt | Task::Split => hints.extend(lang::lang_hint(lang.as_ref(), true)),
+        Task::Sum
+        | Task::Rdm
+        | Task::Section
+        | Task::Contributing
+        | Task::Architecture
+        | Task::Module
+        | Task::Rustdoc => hints.extend(lang::lang_hint(lang.as_ref(), false)),
+        // output is a commit id
+        Task::Fixup => {}
+    }
+    Ok(common_vars(Template::load(task, config)?, pj_path, lang).hints(&hints))
+}
+
+/// variables every template can use. empty outside of a git repo.
+fn common_vars<P: AsRef<Path>>(template: Template, pj_path: P, lang: Option<String>) -> Template {
+    let recent = git::recent_summaries(&pj_path, style::DEFAULT_SAMPLES).unwrap_or_default();
+    template
+        .var("branch", git::current_branch(&pj_path).unwrap_or_default())
+        .var(
+            "files",
+            git::changed_paths(&pj_path).unwrap_or_default().join("\n"),
+        )
+        .var("recent_commits", recent.join("\n"))
+        .var("lang", lang.unwrap_or_else(|| "English".to_string()))
+}
+
+/// scope rules from config and workspace manifests.
+fn resolve_scope_map<P: AsRef<Path>>(pj_path: P, config: &Config) -> scope::ScopeMap {
+    scope::ScopeMap::new(pj_path, &config.scope().clone().unwrap_or_default())
+}
+
+/// `docs` kinds which need llm.
+fn run_doc<P: AsRef<Path>>(
+    action: &DocsAction,
+    cli: &Cli,
+    pj_path: P,
+    config: &Config,
+    model: Model,
+    api_key: Option<String>,
+) -> Result<(), Error> {
+    let pj_path = pj_path.as_ref();
+    if let DocsAction::Rustdoc { path } = action {
+        let path = pj_path.join(path);
+        let code = fs::read_to_string(&path).map_err(Error::IoE)?;
+        let items = doc::undocumented(&code);
+        if items.is_empty() {
+            println!(
+                "all public items of {} have doc comments",
+                path.to_string_lossy()
+            );
+            return Ok(());
+        }
+        let template = resolve_template(Task::Rustdoc, cli, pj_path, config, Vec::new())?
+            .var("items", doc::numbered(&items));
+        let res = readme::create_readme(
+            read_codes::load_codes(&vec![&path])?,
+            template,
+            model,
+            api_key,
+        )?;
+        let new = doc::apply_comments(&code, &items, &res)?;
+        return readme::write_with_preview(&path, code, new, cli.yes);
+    }
+
+    let options = scan::ScanOptions {
+        include: Vec::new(),
+        exclude: Vec::new(),
+        max_size: scan::DEFAULT_MAX_SIZE,
+    };
+    let files = scan::scan(pj_path, &options)?.files;
+    let rel = |p: &String| {
+        Path::new(p)
+            .strip_prefix(pj_path)
+            .map(|r| r.to_string_lossy().to_string())
+            .unwrap_or_else(|_| p.clone())
+    };
+    let (task, options, default_output, context, module) = match action {
+        DocsAction::Contributing(o) => {
+            let tooling = files
+                .iter()
+                .filter(|p| doc::is_contributor_file(&rel(p)))
+                .cloned()
+                .collect::<Vec<_>>();
+            let context = digest::build(pj_path, &tooling, o.budget)?.text();
+            (
+                Task::Contributing,
+                o,
+                "CONTRIBUTING.md".to_string(),
+                context,
+                None,
+            )
+        }
+        DocsAction::Architecture(o) => {
+            let context = digest::build(pj_path, &files, o.budget)?.text();
+            (
+                Task::Architecture,
+                o,
+                "ARCHITECTURE.md".to_string(),
+                context,
+                None,
+            )
+        }
+        DocsAction::Module { path, options: o } => {
+            let full = pj_path.join(path);
+            let target = fs::canonicalize(&full).map_err(Error::IoE)?;
+            let name = doc::module_name(path);
+            // files which refer to the module
+            let users = files
+                .iter()
+                .filter(|p| fs::canonicalize(p).is_ok_and(|c| c != target))
+                .filter(|p| fs::read_to_string(p).is_ok_and(|c| c.contains(&format!("{name}::"))))
+                .cloned()
+                .collect::<Vec<_>>();
+            let context = format!(
+                "{}\n\n{}",
+                read_codes::load_codes(&vec![full])?,
+                digest::build(pj_path, &users, o.budget)?.text()
+            );
+            (
+                Task::Module,
+                o,
+                doc::module_doc_path(path),
+                context,
+                Some(path),
+            )
+        }
+        DocsAction::Cli { .. } | DocsAction::Rustdoc { .. } => unreachable!(),
+    };
+    if options.show_context {
+        println!("{context}");
+        return Ok(());
+    }
+
+    let output = options
+        .output
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| pj_path.join(default_output));
+    // before calling llm
+    if output.exists() && !options.allow_merge && !options.allow_over_write {
+        return Err(Error::ExistsFile(output.to_string_lossy().to_string()));
+    }
+    let current = fs::read_to_string(&output).unwrap_or_else(|_| "(none)".to_string());
+    let template = resolve_template(task, cli, pj_path, config, Vec::new())?
+        .var(
+            "files",
+            files.iter().map(rel).collect::<Vec<_>>().join("\n"),
+        )
+        .var("current", guard::fence("current", current))
+        .var("module", module.cloned().unwrap_or_default());
+    let new = readme::create_readme(context, template, model, api_key)?;
+    readme::save_doc(
+        &output,
+        new,
+        options.allow_merge,
+        options.allow_over_write,
+        cli.yes,
+    )
+}
+
+/// keys are never printed.
+fn run_auth(action: &AuthAction, config: &Config) -> Result<(), Error> {
+    let mut creds = auth::Credentials::load()?;
+    match action {
+        AuthAction::Set { provider } => {
+            let key = if io::stdin().is_terminal() {
+                dialoguer::Password::new()
+                    .with_prompt(format!("api key of {provider}"))
+                    .interact()
+                    .map_err(|e| match e {
+                        dialoguer::Error::IO(e) => Error::IoE(e),
+                    })?
+            } else {
+                io::read_to_string(io::stdin()).map_err(Error::IoE)?
+            };
+            if key.trim().is_empty() {
+                return Err(Error::NotFoundApiKey(provider.to_string()));
+            }
+            creds.set(provider, key);
+            let path = creds.store()?;
+            println!("stored api key of {provider} in {}", path.to_string_lossy());
+            match auth::key_source(provider, config, &creds) {
+                Some(auth::KeySource::Stored) | None => {}
+                Some(s) => println!("note: {s} takes precedence over the stored key"),
+            }
+        }
+        AuthAction::Remove { provider } => {
+            if creds.remove(provider) {
+                creds.store()?;
+                println!("removed api key of {provider}");
+            } else {
+                println!("api key of {provider} is not stored");
+            }
+        }
+        AuthAction::List => {
+            for p in auth::PROVIDERS {
+                let source = auth::key_source(p, config, &creds)
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "not set".to_string());
+                println!("{p}: {source}");
+            }
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
     let cli = Cli::parse();
 
-    // let _config =
-        // config::Config::open::<config::Config>(resolve_config_path()?).map_err(Error::StrE)?;
+    let config = match resolve_config_path() {
+        Ok(p) => Config::open::<Config>(p).map_err(Error::StrE)?,
+        Err(_) => Config::default(),
+    };
+    redact::init(&config.redact().clone().unwrap_or_default())?;
+
+    // no llm needed
+    match &cli.subcommand {
+        Commands::Prompt(p) => {
+            let PromptAction::Show { task, current } = &p.action;
+            let template = if *current {
+                Template::load(*task, &config)?
+            } else {
+                Template::new(*task)
+            };
+            println!("{}", template.render());
+            return Ok(());
+        }
+        Commands::Auth(a) => {
+            run_auth(&a.action, &config)?;
+            return Ok(());
+        }
+        Commands::Docs(DocsCmd {
+            action:
+                DocsAction::Cli {
+                    format,
+                    inject,
+                    block,
+                },
+        }) => {
+            let cmd = Cli::command();
+            let Some(path) = inject else {
+                match format {
+                    cli_doc::DocFormat::Markdown => println!("{}", cli_doc::markdown(&cmd)),
+                    cli_doc::DocFormat::Man => print!("{}", cli_doc::man(&cmd)),
+                }
+                return Ok(());
+            };
+            let old = fs::read_to_string(path).map_err(Error::IoE)?;
+            // a heading of the same name is not replaced
+            if !markdown::has_block(&old, block) {
+                return Err(Error::NotFoundSection(block.to_string()));
+            }
+            let new = markdown::replace(&old, block, cli_doc::markdown(&cmd))
+                .ok_or(Error::NotFoundSection(block.to_string()))?;
+            if new == old {
+                println!("{path} is up to date");
+                return Ok(());
+            }
+            return readme::write_with_preview(path, old, new, cli.yes);
+        }
+        Commands::Cst(cst) if cst.list => {
+            let presets = custom_prompt::list_presets(&config);
+            if presets.is_empty() {
+                println!("no presets. add them to prompt.custom_prompt in config");
+            }
+            presets.iter().for_each(|p| println!("{p}"));
+            return Ok(());
+        }
+        _ => {}
+    }
 
     let pj_path = resolve_work_path(cli.clone())?;
 
@@ -215,21 +992,87 @@ fn main() -> Result<(), Error> {
     // };
 
     let use_model = Model::try_from(cli.clone())?;
+    // fail before reading anything
+    policy::init(&pj_path, &config);
+    policy::check(&use_model.provider).map_err(Error::Llm)?;
 
-    let resolved_api_key = resolve_api_key(&use_model)
-        .transpose()
-        .map_err(Error::EnvE)?;
+    let resolved_api_key = auth::resolve_key(&use_model.provider, &config)?;
 
     match &cli.subcommand {
         Commands::Cmt(commit) => {
+            let (author, committer) = resolve_idents(&cli, &pj_path)?;
+            let co_authors = resolve_co_authors(&cli, &config)?;
+            let (issue_footers, issue_hints) = resolve_issue_refs(&pj_path, &config)?;
+            let style = resolve_style(&pj_path, &config, commit.history, commit.enforce_scopes)?;
+            let paths = if commit.amend {
+                git::amend_paths(&pj_path)?
+            } else {
+                git::changed_paths(&pj_path)?
+            };
+            let scopes = resolve_scope_map(&pj_path, &config).scopes_of(&paths);
+            // inferred scopes take precedence over ones in history
+            let style = style.map(|(s, enforce)| (s, enforce && scopes.is_empty()));
+            let mut hints = match &style {
+                Some((s, enforce)) => [issue_hints, style::style_hints(s, *enforce)].concat(),
+                None => issue_hints,
+            };
+            hints.extend(scope::scope_hint(&scopes));
+            let template = resolve_template(Task::Cmt, &cli, &pj_path, &config, hints)?;
+
             println!("<<<commit mode>>>\n\nread git diff...\ncreating commmit message...");
             let msg = commit_from_gitdiff(
                 &pj_path,
                 use_model,
                 resolved_api_key,
+                commit.amend,
+                template,
                 // commit.auto_commit,
                 // cli.yes,
             )?;
+            let msg = match &style {
+                Some((s, true)) => style::enforce_scope(msg, s),
+                _ => msg,
+            };
+            let msg = scope::check_scope(msg, &scopes);
+            let msg = trailer::append_trailers(msg, &[issue_footers, co_authors].concat());
+
+            println!("created msg:{msg}");
+            let msg = if commit.editor {
+                editor::edit_cmt_msg(&pj_path, &msg)?
+            } else if yes_no("do you edit msg?(y/n)") {
+                Input::new()
+                    .with_prompt("edit")
+                    .default(msg.clone())
+                    .interact_text()
+                    .unwrap()
+            } else {
+                msg
+            };
+
+            if commit.auto_commit || cli.yes || yes_no("\ncontinue?(y/n)>") {
+                if commit.amend {
+                    git::git_commit_amend(
+                        pj_path,
+                        &msg,
+                        author.as_ref(),
+                        &committer,
+                        !cli.no_verify,
+                    )?;
+                } else {
+                    let author = author.map_or_else(|| git::get_ident(&pj_path, "AUTHOR"), Ok)?;
+                    git::git_commit(pj_path, &msg, &author, &committer, !cli.no_verify)?;
+                }
+            }
+        }
+        Commands::Reword(reword) => {
+            let (author, committer) = resolve_idents(&cli, &pj_path)?;
+            let co_authors = resolve_co_authors(&cli, &config)?;
+
+            println!("<<<reword mode>>>\n\nread commit diff...\ncreating commmit message...");
+            let diff = git::get_commit_diff(&pj_path, &reword.rev)?;
+            let template = resolve_template(Task::Cmt, &cli, &pj_path, &config, Vec::new())?;
+            let msg = cmt_msg::create_cmt_msg(diff, template, use_model, resolved_api_key)?;
+            let msg = trailer::append_trailers(msg, &co_authors);
 
             println!("created msg:{msg}");
             let msg = if yes_no("do you edit msg?(y/n)") {
@@ -242,66 +1085,298 @@ fn main() -> Result<(), Error> {
                 msg
             };
 
-            let git_user = git::get_user_email()?;
+            if cli.yes || yes_no(format!("\nreword {}?(y/n)>", reword.rev)) {
+                git::reword_commit(
+                    pj_path,
+                    &reword.rev,
+                    &msg,
+                    author.as_ref(),
+                    &committer,
+                    !cli.no_verify,
+                )?;
+            }
+        }
+        Commands::Fixup(_fixup) => {
+            let (author, committer) = resolve_idents(&cli, &pj_path)?;
+            let author = author.map_or_else(|| git::get_ident(&pj_path, "AUTHOR"), Ok)?;
+            let co_authors = resolve_co_authors(&cli, &config)?;
 
-            if commit.auto_commit || cli.yes || yes_no("\ncontinue?(y/n)>") {
-                git::git_commit(pj_path, &msg, git_user.0, git_user.1)?;
+            println!(
+                "<<<fixup mode>>>\n\nread staged diff and history...\nfinding target commit..."
+            );
+            if git::staged_paths(&pj_path)?.is_empty() {
+                println!("nothing staged. stage the change with `git add` first.");
+                return Ok(());
+            }
+            let template = resolve_template(Task::Fixup, &cli, &pj_path, &config, Vec::new())?;
+            let target = fixup::suggest_fixup(&pj_path, template, use_model, resolved_api_key)?;
+            println!("target: {} {}", target.short_id, target.summary);
+
+            let msg = trailer::append_trailers(format!("fixup! {}", target.summary), &co_authors);
+
+            if cli.yes || yes_no(format!("\ncommit as \"{msg}\"?(y/n)>")) {
+                git::git_commit_staged(pj_path, &msg, &author, &committer, !cli.no_verify)?;
+            }
+        }
+        Commands::Split(_split) => {
+            let (author, committer) = resolve_idents(&cli, &pj_path)?;
+            let author = author.map_or_else(|| git::get_ident(&pj_path, "AUTHOR"), Ok)?;
+            let co_authors = resolve_co_authors(&cli, &config)?;
+            let (issue_footers, issue_hints) = resolve_issue_refs(&pj_path, &config)?;
+            let style = resolve_style(&pj_path, &config, None, false)?;
+            let mut hints = match &style {
+                Some((s, enforce)) => [issue_hints, style::style_hints(s, *enforce)].concat(),
+                None => issue_hints,
+            };
+
+            println!("<<<split mode>>>\n\nread git diff...\ncreating commit plan...");
+            let hunks = git::get_hunks(&pj_path)?;
+            if hunks.is_empty() {
+                println!("nothing to commit");
+                return Ok(());
+            }
+            let scope_map = resolve_scope_map(&pj_path, &config);
+            let mut paths = hunks.iter().map(|h| h.path.as_str()).collect::<Vec<_>>();
+            paths.dedup();
+            hints.extend(scope::path_scope_hint(&scope_map, &paths));
+            let template = resolve_template(Task::Split, &cli, &pj_path, &config, hints)?;
+            let plan = split::create_split_plan(&hunks, template, use_model, resolved_api_key)?;
+
+            println!("created plan:\n{}", split::plan_to_string(&plan, &hunks));
+            let plan = if yes_no("do you edit plan?(y/n)") {
+                let p = split::edit_plan(&pj_path, &plan, &hunks)?;
+                println!("edited plan:\n{}", split::plan_to_string(&p, &hunks));
+                p
+            } else {
+                plan
+            };
+
+            let plan = plan
+                .into_iter()
+                .map(|g| {
+                    let scopes = scope_map.scopes_of(
+                        &g.hunks
+                            .iter()
+                            .filter_map(|i| Some(hunks.get(*i)?.path.as_str()))
+                            .collect::<Vec<_>>(),
+                    );
+                    let message = match &style {
+                        Some((s, true)) if scopes.is_empty() => style::enforce_scope(g.message, s),
+                        _ => g.message,
+                    };
+                    split::Group {
+                        message: trailer::append_trailers(
+                            scope::check_scope(message, &scopes),
+                            &[issue_footers.clone(), co_authors.clone()].concat(),
+                        ),
+                        hunks: g.hunks,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if cli.yes || yes_no("\ncontinue?(y/n)>") {
+                split::apply_plan(pj_path, &plan, &hunks, &author, &committer, !cli.no_verify)?;
             }
         }
         Commands::Sum(_sum) => {
             println!("<<<sumarize mode>>> \n\nread git diff...\nsummarizing diff...");
+            let template = resolve_template(Task::Sum, &cli, &pj_path, &config, Vec::new())?;
             let git_diff = git::get_diff(pj_path)?;
-            let sum = summarize_diff(git_diff, use_model, resolved_api_key)?;
+            let sum = summarize_diff(git_diff, template, use_model, resolved_api_key)?;
             println!("summarize:\n\n{sum}");
         }
         Commands::Rdm(r) => {
-            println!("<<readme mode>>> \n\nread project...\ncreating README");
+            if !r.stdout {
+                println!("<<readme mode>>> \n\nread project...\ncreating README");
+            }
 
             let p = {
                 match r.source_path_list.clone() {
                     Some(v) => v,
                     None => match r.dir.clone() {
                         Some(v) => {
-                            let l = fs::read_dir(v).map_err(Error::IoE)?;
-                            let mut ll = Vec::new();
-                            l.for_each(|i| {
-                                if let Ok(pp) = i {
-                                    ll.push(pp.path().to_string_lossy().to_string());
-                                }
-                            });
-                            ll
+                            let options = scan::ScanOptions {
+                                include: r.include.clone().unwrap_or_default(),
+                                exclude: r.exclude.clone().unwrap_or_default(),
+                                max_size: r.max_size,
+                            };
+                            let s = scan::scan(v, &options)?;
+                            for (path, reason) in &s.skipped {
+                                eprintln!("skipped {path}: {reason}");
+                            }
+                            s.files
                         }
                         None => return Err(Error::NotSettingPath),
                     },
                 }
             };
 
-            let readme_s = readme::create_readme(p.as_ref(), use_model, resolved_api_key)?;
-
-            let save_path = readme::find_readme(&pj_path)
-                .filter(|_| r.allow_merge)
-                .unwrap_or_else(|| {
-                    let now = Local::now().format("%b-%d-%H-%M").to_string();
-                    pj_path.join(now).with_extension("md")
-                });
+            // manifests, license and README of the scanned project, not of the work path
+            let root = r.dir.as_ref().map(PathBuf::from).unwrap_or(pj_path.clone());
+            let context = digest::build(&root, &p, r.budget)?;
+            if r.show_context {
+                println!("{}", context.text());
+                println!(
+                    "\n---\nabout {} tokens. full contents: {}. omitted by budget: {}",
+                    context.tokens(),
+                    context.included.len(),
+                    context.omitted.len()
+                );
+                context.omitted.iter().for_each(|p| println!("  {p}"));
+                return Ok(());
+            }
+            if !context.omitted.is_empty() {
+                eprintln!(
+                    "{} file(s) over the budget are sent as the digest only. see --show-context",
+                    context.omitted.len()
+                );
+            }
 
-            println!("created readme\n{readme_s}");
-            if cli.yes || yes_no(format!("save to {}?", save_path.to_string_lossy())) {
-                let a = if r.allow_merge {
-                    readme::merge_readme(&save_path, r.allow_over_write, readme_s)
-                } else {
-                    readme::save_new_readme(&save_path, r.allow_over_write, readme_s)
+            if let Some(name) = &r.section {
+                let doc = match &r.doc {
+                    Some(d) => PathBuf::from(d),
+                    None => readme::find_readme(&pj_path).ok_or(Error::NotFoundFile)?,
                 };
-                match a {
-                    Ok(_) => println!("success! save to {}", save_path.to_string_lossy()),
-                    Err(e) => return Err(e),
+                let old = fs::read_to_string(&doc).map_err(Error::IoE)?;
+                let current = markdown::content_of(&old, name)
+                    .ok_or(Error::NotFoundSection(name.to_string()))?;
+                let template =
+                    resolve_template(Task::Section, &cli, &pj_path, &config, Vec::new())?
+                        .var("files", p.join("\n"))
+                        .var("section", name)
+                        .var("current", guard::fence("current", current));
+                let content =
+                    readme::create_readme(context.text(), template, use_model, resolved_api_key)?;
+                let new = markdown::replace(&old, name, content)
+                    .ok_or(Error::NotFoundSection(name.to_string()))?;
+                if r.stdout {
+                    print!("{new}");
+                    return Ok(());
                 }
+                return readme::write_with_preview(&doc, old, new, cli.yes);
             }
+
+            let target = match &r.output {
+                Some(o) => PathBuf::from(o),
+                None if r.allow_merge => {
+                    readme::find_readme(&pj_path).unwrap_or_else(|| pj_path.join(readme::README))
+                }
+                None => pj_path.join(readme::GENERATED_README),
+            };
+            // before calling llm
+            if !r.stdout && target.exists() && !r.allow_merge && !r.allow_over_write {
+                return Err(Error::ExistsFile(target.to_string_lossy().to_string()));
+            }
+
+            let template = resolve_template(Task::Rdm, &cli, &pj_path, &config, Vec::new())?
+                .var("files", p.join("\n"));
+            let readme_s =
+                readme::create_readme(context.text(), template, use_model, resolved_api_key)?;
+
+            if r.stdout {
+                let new = match fs::read_to_string(&target) {
+                    Ok(old) if r.allow_merge && !r.allow_over_write => {
+                        markdown::merge(&old, &readme_s)
+                    }
+                    _ => readme_s,
+                };
+                print!("{new}");
+                return Ok(());
+            }
+            readme::save_doc(
+                &target,
+                readme_s,
+                r.allow_merge,
+                r.allow_over_write,
+                cli.yes,
+            )?;
+        }
+        Commands::Chat(c) => {
+            let session = match &c.load {
+                Some(name) => Some(
+                    chat::Session::open::<chat::Session>(chat::session_path(name)?)
+                        .map_err(Error::StrE)?,
+                ),
+                None => None,
+            };
+            chat::run(&pj_path, &config, use_model, resolved_api_key, session)?;
+        }
+        Commands::Docs(d) => {
+            run_doc(
+                &d.action,
+                &cli,
+                &pj_path,
+                &config,
+                use_model,
+                resolved_api_key,
+            )?;
+        }
+        Commands::Prompt(_) | Commands::Auth(_) => {
+            unreachable!("handled before model resolution")
         }
         Commands::Cst(cst) => {
             println!("<<<custom prompt mode>>>");
-            let res = custom_prpmt(cst.clone().preset, use_model, resolved_api_key)?;
+            let preset = cst.preset.as_ref().ok_or(Error::FailedParseCli)?;
+            let pmt = custom_prompt::resolve_preset(preset, cst.raw, &config)?;
+            let template = (!cst.raw).then(|| {
+                let lang = resolve_lang(&cli, &pj_path, &config);
+                common_vars(Template::from_text(&pmt), &pj_path, lang)
+            });
+            let uses = |name| template.as_ref().is_some_and(|t| t.uses(name));
+            let (use_diff, use_code, use_stdin) = (uses("diff"), uses("code"), uses("stdin"));
+
+            // read only what is asked
+            let diff = if cst.diff || use_diff {
+                Some(git::get_diff(&pj_path)?)
+            } else {
+                None
+            };
+            let mut paths = cst.file.clone().unwrap_or_default();
+            if let Some(d) = &cst.dir {
+                paths.extend(read_codes::list_files(d)?);
+            }
+            let code = if paths.is_empty() {
+                None
+            } else {
+                Some(read_codes::load_codes(&paths)?)
+            };
+            let stdin = if io::stdin().is_terminal() {
+                None
+            } else {
+                Some(io::read_to_string(io::stdin()).map_err(Error::IoE)?)
+                    .filter(|s| !s.trim().is_empty())
+            };
+
+            let (system, pmt) = match template {
+                Some(t) => {
+                    let t = if t.uses("staged") {
+                        let staged = git::staged_paths(&pj_path)?;
+                        t.var("staged", staged.join("\n"))
+                    } else {
+                        t
+                    };
+                    let fenced = |label, body: &Option<String>| {
+                        guard::fence(label, body.as_deref().unwrap_or_default())
+                    };
+                    t.var("diff", fenced("diff", &diff))
+                        .var("code", fenced("code", &code))
+                        .var("stdin", fenced("stdin", &stdin))
+                        .render_parts()
+                }
+                None => (None, pmt),
+            };
+            // context not placed by the preset goes after it
+            let blocks = [
+                ("diff", diff.filter(|_| cst.diff && !use_diff)),
+                ("files", code.filter(|_| !use_code)),
+                ("stdin", stdin.filter(|_| !use_stdin)),
+            ];
+            let pmt = custom_prompt::append_context(pmt, &blocks);
+            let system = system.unwrap_or_else(|| guard::DATA_NOTICE.to_string());
+            let res = custom_prpmt(Some(system), pmt.clone(), use_model, resolved_api_key)?;
             println!("\n{res}");
+            // anything in the prompt is expected
+            guard::warn_unexpected(&res, &pmt)?;
         }
     };
     Ok(())
@@ -322,6 +1397,8 @@ mod test {
             &p,
             crate::Model::new("gemini", "gemini-2.0-flash", None, None),
             Some(a),
+            false,
+            crate::Template::new(crate::Task::Cmt),
         );
         println!("{res:?}");
     }
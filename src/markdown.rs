@@ -0,0 +1,445 @@
+use std::ops::Range;
+
+/// a section with this is not changed by merge.
+pub const KEEP_MARKER: &str = "<!-- ggw:keep -->";
+/// closes `<!-- ggw:begin name -->`.
+pub const END_MARKER: &str = "<!-- ggw:end -->";
+
+/// a heading and lines until the next heading.
+/// text before the first heading is a section of level 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub level: usize,
+    pub title: String,
+    pub text: String,
+}
+
+impl Section {
+    /// same title and level, and `nth` among them. titles are compared case-insensitively.
+    fn key(&self, nth: usize) -> (usize, String, usize) {
+        (self.level, self.title.to_lowercase(), nth)
+    }
+
+    fn is_kept(&self) -> bool {
+        self.text.contains(KEEP_MARKER)
+    }
+}
+
+/// `## title` -> (2, "title"). `#` in a code block is not a heading.
+fn heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((
+        level,
+        rest.trim().trim_end_matches('#').trim_end().to_string(),
+    ))
+}
+
+/// sections, and the level 0 section even if it's empty. texts joined are `md`.
+fn parse(md: &str) -> Vec<Section> {
+    let mut sections = vec![Section {
+        level: 0,
+        title: String::new(),
+        text: String::new(),
+    }];
+    let mut fence: Option<&str> = None;
+    for line in md.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(f) if trimmed.starts_with(f) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None => {
+                if let Some((level, title)) = heading(line.trim_end()) {
+                    sections.push(Section {
+                        level,
+                        title,
+                        text: String::new(),
+                    });
+                }
+            }
+        }
+        if let Some(s) = sections.last_mut() {
+            s.text.push_str(line);
+        }
+    }
+    sections
+}
+
+pub fn sections<T: AsRef<str>>(md: T) -> Vec<Section> {
+    let mut sections = parse(md.as_ref());
+    if sections[0].text.trim().is_empty() {
+        sections.remove(0);
+    }
+    sections
+}
+
+fn begin_marker(name: &str) -> String {
+    format!("<!-- ggw:begin {name} -->")
+}
+
+/// between `<!-- ggw:begin name -->` and `<!-- ggw:end -->` lines.
+/// markers in the middle of a line (e.g. in docs about them) are not markers.
+fn find_block(md: &str, name: &str) -> Option<Range<usize>> {
+    let begin = begin_marker(name);
+    let mut offset = 0;
+    let mut start = None;
+    for line in md.split_inclusive('\n') {
+        match start {
+            None if line.trim() == begin => start = Some(offset + line.len()),
+            Some(s) if line.trim() == END_MARKER => return Some(s..offset),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// `<!-- ggw:begin name -->` ... `<!-- ggw:end -->` exists.
+pub fn has_block(md: &str, name: &str) -> bool {
+    find_block(md, name).is_some()
+}
+
+/// lines after the heading until the next heading of the same or upper level.
+fn find_section(md: &str, title: &str) -> Option<Range<usize>> {
+    let sections = parse(md);
+    let mut offset = 0;
+    let mut found: Option<(usize, usize)> = None;
+    for s in &sections {
+        match found {
+            Some((level, start)) if s.level <= level => return Some(start..offset),
+            None if s.level > 0 && s.title.eq_ignore_ascii_case(title.trim()) => {
+                let heading_len = s.text.find('\n').map(|i| i + 1).unwrap_or(s.text.len());
+                found = Some((s.level, offset + heading_len));
+            }
+            _ => {}
+        }
+        offset += s.text.len();
+    }
+    found.map(|(_, start)| start..md.len())
+}
+
+/// managed block of `name`, or section with the heading `name`.
+fn locate(md: &str, name: &str) -> Option<(Range<usize>, bool)> {
+    find_block(md, name)
+        .map(|r| (r, true))
+        .or_else(|| find_section(md, name).map(|r| (r, false)))
+}
+
+/// current content of the block or section.
+pub fn content_of<'a>(md: &'a str, name: &str) -> Option<&'a str> {
+    locate(md, name).map(|(r, _)| &md[r])
+}
+
+/// replace the content of the block or section. `None` when not found.
+/// a heading of `name` at the top of `content` is removed, the heading is kept in `md`.
+pub fn replace<T: AsRef<str>>(md: &str, name: &str, content: T) -> Option<String> {
+    let (range, is_block) = locate(md, name)?;
+    let content = content.as_ref().trim();
+    let content = match content.split_once('\n') {
+        Some((first, rest))
+            if heading(first).is_some_and(|(_, t)| t.eq_ignore_ascii_case(name.trim())) =>
+        {
+            rest.trim()
+        }
+        _ => content,
+    };
+    let content = if is_block {
+        format!("{content}\n")
+    } else if range.end == md.len() {
+        format!("\n{content}\n")
+    } else {
+        format!("\n{content}\n\n")
+    };
+    Some(format!(
+        "{}{content}{}",
+        &md[..range.start],
+        &md[range.end..]
+    ))
+}
+
+type Key = (usize, String, usize);
+
+/// sections with keys. `nth` counts the same title and level.
+fn keyed(sections: Vec<Section>) -> Vec<(Key, Section)> {
+    let mut seen = Vec::new();
+    sections
+        .into_iter()
+        .map(|s| {
+            let nth = seen.iter().filter(|k| *k == &s.key(0)).count();
+            seen.push(s.key(0));
+            (s.key(nth), s)
+        })
+        .collect()
+}
+
+/// index after the subsections of `sections[i]`. the level 0 section has none.
+fn subtree_end(sections: &[(Key, Section)], i: usize) -> usize {
+    let level = sections[i].1.level;
+    if level == 0 {
+        return i + 1;
+    }
+    i + 1
+        + sections[i + 1..]
+            .iter()
+            .take_while(|(_, s)| s.level > level)
+            .count()
+}
+
+/// a part of merged text. `new` ones are separated from others by a blank line.
+struct Part {
+    keys: Vec<Key>,
+    level: usize,
+    text: String,
+    new: bool,
+}
+
+impl Part {
+    fn new(sections: Vec<(Key, &str)>, level: usize) -> Self {
+        let text = sections
+            .iter()
+            .map(|(_, t)| t.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Self {
+            keys: sections.into_iter().map(|(k, _)| k).collect(),
+            level,
+            text: format!("{text}\n"),
+            new: true,
+        }
+    }
+}
+
+/// `new` sections in place of `old` ones, a heading with its subsections.
+/// kept subsections of `old` replace the same one in `new`, or follow the one before them.
+fn replace_subtree(old: &[(Key, Section)], new: Vec<&(Key, Section)>) -> Part {
+    let mut sections = new
+        .iter()
+        .map(|(key, section)| {
+            let text = old[1..]
+                .iter()
+                .find(|(k, s)| k == key && s.is_kept())
+                .map_or(section.text.as_str(), |(_, s)| s.text.as_str());
+            (key.clone(), text)
+        })
+        .collect::<Vec<_>>();
+    for (i, (key, section)) in old.iter().enumerate().skip(1) {
+        if !section.is_kept() || sections.iter().any(|(k, _)| k == key) {
+            continue;
+        }
+        let at = old[..i]
+            .iter()
+            .rev()
+            .find_map(|(k, _)| sections.iter().position(|(s, _)| s == k))
+            .map_or(sections.len(), |a| a + 1);
+        sections.insert(at, (key.clone(), section.text.as_str()));
+    }
+    Part::new(sections, old[0].1.level)
+}
+
+/// sections of `new` replace the same ones in `old` with their subsections.
+/// sections with `KEEP_MARKER` stay. sections only in `old` and not under a replaced one
+/// are copied as is. sections only in `new` go after the section which precedes them
+/// in `new` (with its subsections), or after the first section.
+pub fn merge<T: AsRef<str>>(old: T, new: T) -> String {
+    let old = keyed(sections(old));
+    let new = keyed(sections(new));
+    let mut used = vec![false; new.len()];
+
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < old.len() {
+        let (key, section) = &old[i];
+        let n = new.iter().position(|(k, _)| k == key);
+        match n {
+            Some(n) if !section.is_kept() => {
+                let (end, new_end) = (subtree_end(&old, i), subtree_end(&new, n));
+                let sub = (n..new_end)
+                    .filter(|&j| !std::mem::replace(&mut used[j], true))
+                    .map(|j| &new[j])
+                    .collect();
+                parts.push(replace_subtree(&old[i..end], sub));
+                i = end;
+            }
+            _ => {
+                if let Some(n) = n {
+                    used[n] = true;
+                }
+                parts.push(Part {
+                    keys: vec![key.clone()],
+                    level: section.level,
+                    text: section.text.clone(),
+                    new: false,
+                });
+                i += 1;
+            }
+        }
+    }
+
+    let mut n = 0;
+    while n < new.len() {
+        if used[n] {
+            n += 1;
+            continue;
+        }
+        let end = subtree_end(&new, n);
+        let sub = (n..end)
+            .filter(|&j| !std::mem::replace(&mut used[j], true))
+            .map(|j| (new[j].0.clone(), new[j].1.text.as_str()))
+            .collect();
+        let anchor = new[..n]
+            .iter()
+            .rev()
+            .find_map(|(k, _)| parts.iter().position(|p| p.keys.contains(k)));
+        let at = match anchor {
+            Some(a) => {
+                let level = parts[a].level;
+                // after subsections of the anchor
+                a + 1
+                    + parts[a + 1..]
+                        .iter()
+                        .take_while(|p| level != 0 && p.level > level)
+                        .count()
+            }
+            None => parts.len().min(1),
+        };
+        parts.insert(at, Part::new(sub, new[n].1.level));
+        n = end;
+    }
+
+    let mut text = String::new();
+    let mut prev_new = false;
+    for p in parts {
+        // only appended, not to change the text of old sections
+        while !text.is_empty() && (p.new || prev_new) && !text.ends_with("\n\n") {
+            text.push('\n');
+        }
+        text.push_str(&p.text);
+        prev_new = p.new;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_of, merge, replace, sections};
+
+    #[test]
+    fn test_sections() {
+        let md = "badge\n# app\nintro\n```sh\n# not heading\n```\n## usage\nrun\n";
+        let s = sections(md);
+        assert_eq!(
+            s.iter()
+                .map(|s| (s.level, s.title.as_str()))
+                .collect::<Vec<_>>(),
+            [(0, ""), (1, "app"), (2, "usage")]
+        );
+        assert_eq!(s[1].text, "# app\nintro\n```sh\n# not heading\n```\n");
+    }
+
+    #[test]
+    fn test_merge() {
+        let old = "# app
+
+old intro
+
+## Install
+
+cargo install app
+
+### from source
+
+make
+
+### manual
+<!-- ggw:keep -->
+copy the binary
+
+## Notes
+<!-- ggw:keep -->
+hand written
+";
+        let new = "# app
+
+new intro
+
+## Install
+
+cargo install app --locked
+
+## Usage
+
+app run
+
+## Notes
+
+generated notes
+";
+        assert_eq!(
+            merge(old, new),
+            "# app
+
+new intro
+
+## Install
+
+cargo install app --locked
+
+### manual
+<!-- ggw:keep -->
+copy the binary
+
+## Usage
+
+app run
+
+## Notes
+<!-- ggw:keep -->
+hand written
+"
+        );
+
+        // sections not replaced are copied as is
+        let old = "intro\n## Install\n\nold\n## Legacy\n\n\n  spaced  \n\n\n## License\nMIT";
+        let new = "## Install\n\nnew\n\n## FAQ\n\nnone\n";
+        assert_eq!(
+            merge(old, new),
+            "intro\n\n## Install\n\nnew\n\n## FAQ\n\nnone\n\n## Legacy\n\n\n  spaced  \n\n\n## License\nMIT"
+        );
+    }
+
+    #[test]
+    fn test_replace() {
+        let md = "# app\n\n## Usage\n\nold\n\n### flags\n\n-v\n\n## License\n\nMIT\n";
+        assert_eq!(
+            content_of(md, "usage"),
+            Some("\nold\n\n### flags\n\n-v\n\n")
+        );
+        assert_eq!(
+            replace(md, "Usage", "## Usage\n\nnew\n").unwrap(),
+            "# app\n\n## Usage\n\nnew\n\n## License\n\nMIT\n"
+        );
+        assert_eq!(
+            replace(md, "License", "Apache-2.0").unwrap(),
+            "# app\n\n## Usage\n\nold\n\n### flags\n\n-v\n\n## License\n\nApache-2.0\n"
+        );
+
+        let doc = "intro\n<!-- ggw:begin options -->\nold\n<!-- ggw:end -->\nrest\n";
+        assert_eq!(content_of(doc, "options"), Some("old\n"));
+        assert_eq!(
+            replace(doc, "options", "| a | b |").unwrap(),
+            "intro\n<!-- ggw:begin options -->\n| a | b |\n<!-- ggw:end -->\nrest\n"
+        );
+        assert!(replace(doc, "missing", "x").is_none());
+
+        let doc = "see `<!-- ggw:begin cli -->`\n<!-- ggw:begin cli -->\n<!-- ggw:end -->\n";
+        assert_eq!(
+            replace(doc, "cli", "new").unwrap(),
+            "see `<!-- ggw:begin cli -->`\n<!-- ggw:begin cli -->\nnew\n<!-- ggw:end -->\n"
+        );
+    }
+}
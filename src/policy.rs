@@ -0,0 +1,107 @@
+use std::{path::Path, sync::OnceLock};
+
+use crate::{
+    config::Config,
+    git,
+    llm::{self, LlmError},
+};
+
+/// providers allowed in the repo. `None` allows all.
+static ALLOWED: OnceLock<Option<Vec<String>>> = OnceLock::new();
+
+/// lists from every source are intersected, so the strictest one wins.
+/// `None` when no source restricts providers.
+fn allowed_providers(local_only: bool, lists: Vec<Vec<String>>) -> Option<Vec<String>> {
+    let local = local_only.then(|| llm::LOCAL_PROVIDERS.map(|p| p.to_string()).to_vec());
+    lists
+        .into_iter()
+        .chain(local)
+        .map(|l| {
+            l.iter()
+                .map(|p| p.trim().to_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .reduce(|acc, l| acc.into_iter().filter(|p| l.contains(p)).collect())
+}
+
+/// policy of the repo which contains `project_path`, also from its subdirectories.
+/// `ggw.localOnly` and `ggw.allowedProviders` (comma separated) in git config, and `policy` in config.
+pub fn init<P: AsRef<Path>>(project_path: P, config: &Config) {
+    let _ = ALLOWED.set(resolve(project_path, config));
+}
+
+fn resolve<P: AsRef<Path>>(project_path: P, config: &Config) -> Option<Vec<String>> {
+    let policy = config.policy().clone().unwrap_or_default();
+    let local_only = git::get_config_bool(&project_path, "ggw.localOnly").unwrap_or(false)
+        || policy.local_only().unwrap_or(false);
+    let lists = git::get_config(&project_path, "ggw.allowedProviders")
+        .map(|l| l.split(',').map(|p| p.to_string()).collect())
+        .into_iter()
+        .chain(policy.allowed_providers().clone())
+        .collect();
+    allowed_providers(local_only, lists)
+}
+
+/// called before every request.
+pub fn check<T: AsRef<str>>(provider: T) -> Result<(), LlmError> {
+    let Some(allowed) = ALLOWED.get().and_then(|a| a.as_ref()) else {
+        return Ok(());
+    };
+    let provider = provider.as_ref().to_lowercase();
+    if allowed.contains(&provider) {
+        return Ok(());
+    }
+    let allowed = if allowed.is_empty() {
+        "none".to_string()
+    } else {
+        allowed.join(", ")
+    };
+    Err(LlmError::Refused(format!(
+        "provider '{provider}' is not allowed in this repository (allowed: {allowed}). see `ggw.allowedProviders`/`ggw.localOnly` in git config and `policy` in config"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use git2::Repository;
+
+    use super::{allowed_providers, resolve};
+    use crate::config::Config;
+
+    #[test]
+    fn test_allowed_providers() {
+        let list = |l: &[&str]| l.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(allowed_providers(false, vec![]), None);
+        assert_eq!(allowed_providers(true, vec![]), Some(list(&["ollama"])));
+        assert_eq!(
+            allowed_providers(false, vec![list(&["Ollama", " openai"]), list(&["openai"])]),
+            Some(list(&["openai"]))
+        );
+        assert_eq!(
+            allowed_providers(true, vec![list(&["openai"])]),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_policy_from_subdir() {
+        let dir = env::temp_dir().join(format!("ggw_policy_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_bool("ggw.localOnly", true).unwrap();
+        config
+            .set_str("ggw.allowedProviders", "ollama,openai")
+            .unwrap();
+        let sub = dir.join("src/nested");
+        fs::create_dir_all(&sub).unwrap();
+
+        let config = Config::default();
+        let expected = Some(vec!["ollama".to_string()]);
+        assert_eq!(resolve(&dir, &config), expected);
+        assert_eq!(resolve(&sub, &config), expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
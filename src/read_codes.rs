@@ -16,3 +16,16 @@ pub fn load_codes<P: AsRef<Path>>(path_list: &Vec<P>) -> Result<String, Error> {
 
     Ok(file_contents.into_iter().collect::<String>())
 }
+
+/// files directly under dir. sorted.
+pub fn list_files<P: AsRef<Path>>(dir: P) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(Error::IoE)? {
+        let path = entry.map_err(Error::IoE)?.path();
+        if path.is_file() {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,411 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::Repository;
+
+use crate::Error;
+
+/// skipped unless re-included by `!` in an ignore file.
+const DEFAULT_IGNORES: [&str; 3] = [".git/", "target/", "node_modules/"];
+/// default of `--max-size`. bytes.
+pub const DEFAULT_MAX_SIZE: u64 = 100_000;
+
+pub struct ScanOptions {
+    /// globs. only matching files when not empty.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub max_size: u64,
+}
+
+/// files to read, and files skipped with the reason.
+pub struct Scan {
+    pub files: Vec<String>,
+    pub skipped: Vec<(String, &'static str)>,
+}
+
+/// a line of `.gitignore` style file. see `reference/ignore.md` for the supported syntax.
+struct Rule {
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let mut line = line.trim_end_matches(['\r', '\n']);
+        // trailing spaces are ignored unless escaped
+        while line.ends_with(' ') && !line.ends_with("\\ ") {
+            line = &line[..line.len() - 1];
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+        Some(Self {
+            pattern: line.to_string(),
+            negate,
+            dir_only,
+        })
+    }
+}
+
+/// ignore rules of each directory, read on first use.
+struct Ignores {
+    root: PathBuf,
+    /// `.gitignore` is not read in a repo. git already applied it.
+    files: &'static [&'static str],
+    rules: HashMap<String, Vec<Rule>>,
+}
+
+impl Ignores {
+    fn new(root: &Path, in_repo: bool) -> Self {
+        let files: &[&str] = if in_repo {
+            &[".ggwignore"]
+        } else {
+            &[".gitignore", ".ggwignore"]
+        };
+        Self {
+            root: root.to_path_buf(),
+            files,
+            rules: HashMap::new(),
+        }
+    }
+
+    fn rules_of(&mut self, dir: &str) -> &Vec<Rule> {
+        self.rules.entry(dir.to_string()).or_insert_with(|| {
+            // later rules win. defaults < .gitignore < .ggwignore
+            let mut texts = dir
+                .is_empty()
+                .then(|| DEFAULT_IGNORES.join("\n"))
+                .into_iter()
+                .collect::<Vec<_>>();
+            texts.extend(
+                self.files
+                    .iter()
+                    .filter_map(|f| fs::read_to_string(self.root.join(dir).join(f)).ok()),
+            );
+            texts
+                .iter()
+                .flat_map(|t| t.lines().filter_map(Rule::parse))
+                .collect()
+        })
+    }
+
+    /// `rel` is relative to the root with `/`. the last matching rule wins,
+    /// and rules of deeper directories come later.
+    fn is_ignored(&mut self, rel: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        let mut dir = String::new();
+        for comp in rel.split('/') {
+            let sub = rel.strip_prefix(&format!("{dir}/")).unwrap_or(rel);
+            for r in self.rules_of(&dir) {
+                if (!r.dir_only || is_dir) && path_match(&r.pattern, sub) {
+                    ignored = !r.negate;
+                }
+            }
+            dir = join(&dir, comp);
+        }
+        ignored
+    }
+
+    /// a file in an ignored directory is ignored.
+    fn is_excluded(&mut self, rel: &str) -> bool {
+        let comps = rel.split('/').collect::<Vec<_>>();
+        (1..=comps.len()).any(|i| self.is_ignored(&comps[..i].join("/"), i < comps.len()))
+    }
+}
+
+fn join(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else {
+        format!("{base}/{name}")
+    }
+}
+
+/// `*`, `?` and `[...]` don't match `/`, `**` does. `\` escapes the next char.
+/// without `/`, the pattern matches the file name. otherwise it's anchored to the base.
+pub fn path_match(pattern: &str, path: &str) -> bool {
+    match pattern.strip_prefix('/') {
+        Some(p) => glob(p, path),
+        None if pattern.contains('/') => glob(pattern, path),
+        None => glob(pattern, path.rsplit('/').next().unwrap_or(path)),
+    }
+}
+
+fn glob(pattern: &str, s: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        let rest = rest.strip_prefix('/').unwrap_or(rest);
+        return rest.is_empty()
+            || (0..=s.len())
+                .filter(|&i| s.is_char_boundary(i) && (i == 0 || s[..i].ends_with('/')))
+                .any(|i| glob(rest, &s[i..]));
+    }
+    // `[` without `]` is a literal
+    if let Some((set, negate, rest)) = pattern.strip_prefix('[').and_then(class) {
+        return s.chars().next().is_some_and(|c| {
+            c != '/' && class_match(set, c) != negate && glob(rest, &s[c.len_utf8()..])
+        });
+    }
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => s.is_empty(),
+        Some('*') => (0..=s.len())
+            .filter(|&i| s.is_char_boundary(i) && !s[..i].contains('/'))
+            .any(|i| glob(chars.as_str(), &s[i..])),
+        Some('?') => s
+            .chars()
+            .next()
+            .is_some_and(|c| c != '/' && glob(chars.as_str(), &s[c.len_utf8()..])),
+        Some('\\') => {
+            let c = chars.next().unwrap_or('\\');
+            s.strip_prefix(c)
+                .is_some_and(|rest| glob(chars.as_str(), rest))
+        }
+        Some(c) => s
+            .strip_prefix(c)
+            .is_some_and(|rest| glob(chars.as_str(), rest)),
+    }
+}
+
+/// `!a-z]rest` -> (`a-z`, negated, `rest`). `None` without the closing `]`.
+/// `]` right after `[` or `[!` is a member.
+fn class(pattern: &str) -> Option<(&str, bool, &str)> {
+    let (negate, body) = match pattern.strip_prefix(['!', '^']) {
+        Some(b) => (true, b),
+        None => (false, pattern),
+    };
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ']' if i > 0 => return Some((&body[..i], negate, &body[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// members and ranges like `a-z` of a class.
+fn class_match(set: &str, c: char) -> bool {
+    let mut members = Vec::new();
+    let mut chars = set.chars();
+    while let Some(m) = chars.next() {
+        members.push(if m == '\\' {
+            chars.next().unwrap_or(m)
+        } else {
+            m
+        });
+    }
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            if (members[i]..=members[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if members[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// tracked files under `dir`, relative to it. `None` outside a repo.
+fn tracked_files(dir: &Path) -> Option<Vec<String>> {
+    let repo = Repository::discover(dir).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let dir = dir.canonicalize().ok()?;
+    let base = dir
+        .strip_prefix(&workdir)
+        .ok()?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let index = repo.index().ok()?;
+    Some(
+        index
+            .iter()
+            .filter_map(|e| {
+                let path = String::from_utf8_lossy(&e.path).to_string();
+                if base.is_empty() {
+                    Some(path)
+                } else {
+                    path.strip_prefix(&format!("{base}/"))
+                        .map(|p| p.to_string())
+                }
+            })
+            .collect(),
+    )
+}
+
+/// files under `dir`, relative to it. ignored directories are not entered.
+fn walk_files(dir: &Path, rel: &str, ignores: &mut Ignores) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir.join(rel)).map_err(Error::IoE)? {
+        let entry = entry.map_err(Error::IoE)?;
+        let path = join(rel, &entry.file_name().to_string_lossy());
+        // symlinks are not followed
+        let ty = entry.file_type().map_err(Error::IoE)?;
+        if ty.is_dir() && !ignores.is_ignored(&path, true) {
+            files.extend(walk_files(dir, &path, ignores)?);
+        } else if ty.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// source files under `dir`. tracked files when in a git repo, otherwise a recursive walk.
+/// `.gitignore`, `.ggwignore`, globs, size and binary files are checked.
+pub fn scan<P: AsRef<Path>>(dir: P, options: &ScanOptions) -> Result<Scan, Error> {
+    let dir = dir.as_ref();
+    let tracked = tracked_files(dir);
+    let mut ignores = Ignores::new(dir, tracked.is_some());
+    let mut paths = match tracked {
+        Some(t) => t,
+        None => walk_files(dir, "", &mut ignores)?,
+    };
+    paths.sort();
+
+    let mut scan = Scan {
+        files: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for rel in paths {
+        if ignores.is_excluded(&rel)
+            || (!options.include.is_empty() && !options.include.iter().any(|g| path_match(g, &rel)))
+            || options.exclude.iter().any(|g| path_match(g, &rel))
+        {
+            continue;
+        }
+        let path = dir.join(&rel);
+        // deleted but still in the index
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        let path = path.to_string_lossy().to_string();
+        if meta.len() > options.max_size {
+            scan.skipped.push((path, "too large"));
+            continue;
+        }
+        match fs::read(&path) {
+            Ok(b) if !b.contains(&0) && std::str::from_utf8(&b).is_ok() => scan.files.push(path),
+            Ok(_) => scan.skipped.push((path, "binary or not UTF-8")),
+            Err(_) => scan.skipped.push((path, "unreadable")),
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{DEFAULT_MAX_SIZE, Rule, ScanOptions, path_match, scan};
+
+    #[test]
+    fn test_path_match() {
+        assert!(path_match("*.rs", "src/main.rs"));
+        assert!(!path_match("src/*.rs", "src/a/main.rs"));
+        assert!(path_match("src/**/*.rs", "src/a/main.rs"));
+        assert!(path_match("src/**/*.rs", "src/main.rs"));
+        assert!(path_match("/docs", "docs"));
+        assert!(!path_match("/docs", "a/docs"));
+        assert!(path_match("a?c", "x/abc"));
+        // the subset in reference/ignore.md
+        assert!(path_match("foo/**", "foo/a/b.rs"));
+        assert!(!path_match("foo/**", "foo"));
+        assert!(!path_match("foo/**", "x/foo/a"));
+        assert!(path_match("**/foo", "x/y/foo"));
+        assert!(path_match("a/**/b", "a/b") && path_match("a/**/b", "a/x/y/b"));
+        assert!(path_match("*.[oa]", "x/lib.a") && !path_match("*.[oa]", "lib.c"));
+        assert!(path_match("v[0-9]", "v7") && !path_match("v[!0-9]", "v7"));
+        assert!(path_match("[]x]", "]") && !path_match("a[/]b", "a/b"));
+        assert!(path_match("\\#note", "#note") && path_match("\\*", "*"));
+        assert!(!path_match("\\*", "a"));
+        assert!(path_match("[ab", "[ab"));
+
+        assert_eq!(Rule::parse("a.log  ").unwrap().pattern, "a.log");
+        assert_eq!(Rule::parse("a\\ ").unwrap().pattern, "a\\ ");
+        let r = Rule::parse("!build/").unwrap();
+        assert!(r.negate && r.dir_only && r.pattern == "build");
+        assert!(Rule::parse("# comment").is_none());
+    }
+
+    #[test]
+    fn test_scan() {
+        let dir = env::temp_dir().join(format!("ggw_scan_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, body) in [
+            ("src/main.rs", "fn main() {}"),
+            ("src/nested/lib.rs", "pub fn a() {}"),
+            ("target/debug/out.rs", "x"),
+            ("node_modules/a/index.js", "x"),
+            ("app.log", "x"),
+            ("keep.log", "x"),
+            ("secret/key.txt", "x"),
+            ("logo.png", "\0PNG"),
+            ("big.txt", &"a".repeat(200)),
+            (".gitignore", "*.log\n!keep.log\n"),
+            ("src/.ggwignore", "nested/\n"),
+        ] {
+            let p = dir.join(path);
+            fs::create_dir_all(p.parent().unwrap()).unwrap();
+            fs::write(p, body).unwrap();
+        }
+        fs::write(dir.join(".ggwignore"), "secret/\n").unwrap();
+
+        let options = ScanOptions {
+            include: Vec::new(),
+            exclude: vec!["*.txt".to_string()],
+            max_size: 100,
+        };
+        let res = scan(&dir, &options).unwrap();
+        let rel = |v: &[String]| {
+            v.iter()
+                .map(|p| {
+                    p.strip_prefix(&format!("{}/", dir.to_string_lossy()))
+                        .unwrap()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            rel(&res.files),
+            [
+                ".ggwignore",
+                ".gitignore",
+                "keep.log",
+                "src/.ggwignore",
+                "src/main.rs"
+            ]
+        );
+        assert_eq!(res.skipped.len(), 1);
+        assert!(res.skipped[0].0.ends_with("logo.png"));
+
+        let options = ScanOptions {
+            include: vec!["**/*.rs".to_string()],
+            exclude: Vec::new(),
+            max_size: DEFAULT_MAX_SIZE,
+        };
+        assert_eq!(rel(&scan(&dir, &options).unwrap().files), ["src/main.rs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
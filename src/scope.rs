@@ -0,0 +1,319 @@
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use crate::{Error, config::ScopeRule, conventional};
+
+/// path prefix -> scope. the longest matching prefix wins.
+#[derive(Debug, Default)]
+pub struct ScopeMap {
+    rules: Vec<(String, String)>,
+    top_level_dir: bool,
+}
+
+impl ScopeMap {
+    /// rules from config come first, then members of Cargo/npm workspace.
+    pub fn new<P: AsRef<Path>>(project_path: P, config: &ScopeRule) -> Self {
+        let mut rules = config
+            .paths()
+            .iter()
+            .flatten()
+            .map(|(p, s)| (normalize(p), s.clone()))
+            .collect::<Vec<_>>();
+        if config.workspace().unwrap_or(true) {
+            for member in workspace_members(&project_path) {
+                if !rules.iter().any(|(p, _)| *p == member.0) {
+                    rules.push(member);
+                }
+            }
+        }
+        Self {
+            rules,
+            top_level_dir: config.top_level_dir().unwrap_or(false),
+        }
+    }
+
+    pub fn scope_of<T: AsRef<str>>(&self, path: T) -> Option<String> {
+        let path = path.as_ref();
+        self.rules
+            .iter()
+            .filter(|(p, _)| p.is_empty() || path == p || path.starts_with(&format!("{p}/")))
+            .max_by_key(|(p, _)| p.len())
+            .map(|(_, s)| s.clone())
+            .or_else(|| {
+                // a file at the root has no scope
+                let (dir, _) = path.split_once('/').filter(|_| self.top_level_dir)?;
+                Some(dir.to_string())
+            })
+    }
+
+    /// sorted and deduped scopes of paths.
+    pub fn scopes_of<T: AsRef<str>>(&self, paths: &[T]) -> Vec<String> {
+        let mut scopes = paths
+            .iter()
+            .filter_map(|p| self.scope_of(p))
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes.dedup();
+        scopes
+    }
+}
+
+/// `./crates/a/` -> `crates/a`. the root (`.`) is an empty path, which matches all paths.
+fn normalize(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if path == "." {
+        String::new()
+    } else {
+        path.to_string()
+    }
+}
+
+/// `(dir, name)` of workspace members in Cargo.toml and package.json at project root.
+fn workspace_members<P: AsRef<Path>>(project_path: P) -> Vec<(String, String)> {
+    let root = project_path.as_ref();
+    [cargo_members(root), npm_members(root)]
+        .into_iter()
+        .flat_map(|m| {
+            m.unwrap_or_else(|e| {
+                eprintln!("warning: {e}. scopes of its members are not inferred.");
+                Vec::new()
+            })
+        })
+        .collect()
+}
+
+fn cargo_members(root: &Path) -> Result<Vec<(String, String)>, Error> {
+    let mut members = Vec::new();
+    if let Ok(s) = fs::read_to_string(root.join("Cargo.toml")) {
+        let manifest = s
+            .parse::<toml::Table>()
+            .map_err(|e| Error::InvalidManifest(format!("Cargo.toml: {e}")))?;
+        let patterns = manifest
+            .get("workspace")
+            .and_then(|w| w.get("members"))
+            .and_then(|m| m.as_array())
+            .map(|a| a.iter().filter_map(|m| m.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for dir in expand_members(root, &patterns)? {
+            let name = fs::read_to_string(root.join(&dir).join("Cargo.toml"))
+                .ok()
+                .and_then(|s| s.parse::<toml::Table>().ok())
+                .and_then(|t| Some(t.get("package")?.get("name")?.as_str()?.to_string()));
+            members.extend(member(dir, name));
+        }
+    }
+    Ok(members)
+}
+
+fn npm_members(root: &Path) -> Result<Vec<(String, String)>, Error> {
+    let mut members = Vec::new();
+    if let Ok(s) = fs::read_to_string(root.join("package.json")) {
+        let manifest = serde_json::from_str::<Value>(&s)
+            .map_err(|e| Error::InvalidManifest(format!("package.json: {e}")))?;
+        // `"workspaces": [...]` or `"workspaces": { "packages": [...] }` (yarn)
+        let patterns = manifest
+            .get("workspaces")
+            .and_then(|w| w.as_array().or_else(|| w.get("packages")?.as_array()))
+            .map(|a| a.iter().filter_map(|m| m.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for dir in expand_members(root, &patterns)? {
+            let name = fs::read_to_string(root.join(&dir).join("package.json"))
+                .ok()
+                .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+                .and_then(|v| {
+                    let name = v.get("name")?.as_str()?;
+                    // `@org/foo` -> `foo`
+                    Some(name.rsplit('/').next().unwrap_or(name).to_string())
+                });
+            members.extend(member(dir, name));
+        }
+    }
+    Ok(members)
+}
+
+/// directory name is used when the manifest has no name. `None` for the root without a name.
+fn member(dir: String, name: Option<String>) -> Option<(String, String)> {
+    let name = name.unwrap_or_else(|| dir.rsplit('/').next().unwrap_or(&dir).to_string());
+    Some((dir, name)).filter(|(_, n)| !n.is_empty())
+}
+
+/// expand `*` in each path component. `!` patterns exclude.
+fn expand_members(root: &Path, patterns: &[&str]) -> Result<Vec<String>, Error> {
+    let mut dirs: Vec<String> = Vec::new();
+    for pattern in patterns.iter().filter(|p| !p.starts_with('!')) {
+        let mut found = vec![String::new()];
+        for comp in normalize(pattern).split('/') {
+            let mut next = Vec::new();
+            for base in found {
+                if !comp.contains('*') {
+                    next.push(join(&base, comp));
+                    continue;
+                }
+                let Ok(entries) = fs::read_dir(root.join(&base)) else {
+                    continue;
+                };
+                for entry in entries {
+                    let entry = entry.map_err(Error::IoE)?;
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_dir() && !name.starts_with('.') && glob_match(comp, &name) {
+                        next.push(join(&base, &name));
+                    }
+                }
+            }
+            found = next;
+        }
+        dirs.extend(found.into_iter().filter(|d| root.join(d).is_dir()));
+    }
+    dirs.retain(|d| {
+        !patterns
+            .iter()
+            .filter_map(|p| p.strip_prefix('!'))
+            .any(|p| normalize(p) == *d)
+    });
+    dirs.sort();
+    dirs.dedup();
+    Ok(dirs)
+}
+
+fn join(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else {
+        format!("{base}/{name}")
+    }
+}
+
+/// `*` matches any characters in a path component.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((head, tail)) => {
+            let Some(rest) = name.strip_prefix(head) else {
+                return false;
+            };
+            (0..=rest.len())
+                .filter(|i| rest.is_char_boundary(*i))
+                .any(|i| glob_match(tail, &rest[i..]))
+        }
+    }
+}
+
+/// a line for prompt.
+pub fn scope_hint(scopes: &[String]) -> Option<String> {
+    match scopes {
+        [] => None,
+        [s] => Some(format!("Use \"{s}\" as the scope.")),
+        s => Some(format!(
+            "The change touches these scopes: {}. Use one of them as the scope, or omit the scope if the change is not specific to one.",
+            s.join(", ")
+        )),
+    }
+}
+
+/// prompt line for split. llm picks scopes by the path of hunks.
+pub fn path_scope_hint<T: AsRef<str>>(map: &ScopeMap, paths: &[T]) -> Option<String> {
+    let lines = paths
+        .iter()
+        .filter_map(|p| Some(format!("- {}: {}", p.as_ref(), map.scope_of(p)?)))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Scopes are decided by file paths. Use the scope of the files in each commit, or omit the scope if they have different ones:\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// fix the scope of msg to computed ones. one scope is always set,
+/// with several ones a scope out of them is removed.
+pub fn check_scope(msg: String, scopes: &[String]) -> String {
+    let Some(header) = conventional::parse_header(&msg) else {
+        return msg;
+    };
+    match (scopes, header.scope) {
+        ([], _) => msg,
+        ([s], Some(used)) if s == used => msg,
+        ([s], used) => {
+            if let Some(u) = used {
+                println!("scope \"{u}\" is replaced with \"{s}\" inferred from changed paths.");
+            }
+            conventional::set_scope(&msg, Some(s))
+        }
+        (s, Some(used)) if !s.iter().any(|s| s == used) => {
+            println!("scope \"{used}\" doesn't match changed paths. removed.");
+            conventional::set_scope(&msg, None)
+        }
+        _ => msg,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{ScopeMap, check_scope, glob_match};
+    use crate::config::ScopeRule;
+
+    #[test]
+    fn test_scope_of() {
+        let map = ScopeMap {
+            rules: vec![
+                ("crates/core".to_string(), "core".to_string()),
+                ("crates/core/macros".to_string(), "macros".to_string()),
+                ("docs".to_string(), "docs".to_string()),
+            ],
+            top_level_dir: true,
+        };
+        assert_eq!(map.scope_of("crates/core/src/lib.rs").unwrap(), "core");
+        assert_eq!(map.scope_of("crates/core/macros/lib.rs").unwrap(), "macros");
+        assert_eq!(map.scope_of("crates/core2/lib.rs").unwrap(), "crates");
+        assert_eq!(map.scope_of("README.md"), None);
+        assert_eq!(
+            map.scopes_of(&["docs/a.md", "docs/b.md", "crates/core/x.rs"]),
+            vec!["core", "docs"]
+        );
+        assert!(glob_match("ggw-*", "ggw-cli"));
+        assert!(!glob_match("ggw-*", "cli"));
+    }
+
+    #[test]
+    fn test_workspace_members() {
+        let dir = env::temp_dir().join(format!("ggw_scope_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("crates/cli")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"app\"\n[workspace]\nmembers = [\".\", \"crates/*\"]\n",
+        )
+        .unwrap();
+        fs::write(dir.join("package.json"), "{ broken").unwrap();
+        let map = ScopeMap::new(&dir, &ScopeRule::default());
+        assert_eq!(map.scope_of("src/main.rs").unwrap(), "app");
+        assert_eq!(map.scope_of("crates/cli/src/lib.rs").unwrap(), "cli");
+
+        fs::write(dir.join("Cargo.toml"), "[workspace").unwrap();
+        let map = ScopeMap::new(&dir, &ScopeRule::default());
+        assert_eq!(map.scope_of("crates/cli/src/lib.rs"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_scope() {
+        let one = ["core".to_string()];
+        assert_eq!(
+            check_scope("feat: add".to_string(), &one),
+            "feat(core): add"
+        );
+        assert_eq!(
+            check_scope("feat(x): add".to_string(), &one),
+            "feat(core): add"
+        );
+
+        let many = ["cli".to_string(), "core".to_string()];
+        assert_eq!(check_scope("fix(cli): a".to_string(), &many), "fix(cli): a");
+        assert_eq!(check_scope("fix(x): a".to_string(), &many), "fix: a");
+        assert_eq!(check_scope("fix(x): a".to_string(), &[]), "fix(x): a");
+    }
+}
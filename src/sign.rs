@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use git2::{Repository, Signature};
+
+use crate::Error;
+
+/// sign commit buffer like git. `gpg.format` is `openpgp`(default), `x509` or `ssh`.
+pub fn sign_buffer(repo: &Repository, buf: &str, committer: &Signature) -> Result<String, Error> {
+    let config = repo.config().map_err(Error::GitE)?;
+    let format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".to_string());
+    let key = config.get_string("user.signingkey").ok();
+
+    match format.as_str() {
+        "ssh" => {
+            let program = config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string());
+            let key = key.ok_or(Error::Sign(
+                "user.signingkey is required for ssh signing".to_string(),
+            ))?;
+            ssh_sign(repo, &program, &key, buf)
+        }
+        f => {
+            let program = config
+                .get_string(&format!("gpg.{f}.program"))
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| if f == "x509" { "gpgsm" } else { "gpg" }.to_string());
+            // git uses committer ident when signingkey is not set.
+            let key = key.unwrap_or_else(|| {
+                format!(
+                    "{} <{}>",
+                    committer.name().unwrap_or_default(),
+                    committer.email().unwrap_or_default()
+                )
+            });
+            gpg_sign(&program, &key, buf)
+        }
+    }
+}
+
+fn gpg_sign(program: &str, key: &str, buf: &str) -> Result<String, Error> {
+    let mut child = Command::new(program)
+        .args(["--status-fd=2", "-bsau", key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(Error::IoE)?;
+
+    child
+        .stdin
+        .take()
+        .ok_or(Error::Sign(format!("failed to open stdin of {program}")))?
+        .write_all(buf.as_bytes())
+        .map_err(Error::IoE)?;
+
+    let out = child.wait_with_output().map_err(Error::IoE)?;
+    if out.status.success() {
+        String::from_utf8(out.stdout).map_err(|e| Error::Sign(e.to_string()))
+    } else {
+        Err(Error::Sign(format!("{program} failed to sign the data")))
+    }
+}
+
+/// `user.signingkey` is a key file path, or `key::<public key>` to use ssh-agent.
+fn ssh_sign(repo: &Repository, program: &str, key: &str, buf: &str) -> Result<String, Error> {
+    let buf_path = repo.path().join("GGW_COMMIT_BUFFER");
+    let sig_path = buf_path.with_extension("sig");
+    let literal_key_path = repo.path().join("GGW_SIGNING_KEY.pub");
+
+    let key_path = match key.strip_prefix("key::") {
+        Some(k) => {
+            fs::write(&literal_key_path, k).map_err(Error::IoE)?;
+            literal_key_path.clone()
+        }
+        None => match key.strip_prefix("~/") {
+            Some(rest) => home::home_dir().ok_or(Error::NotFoundHome)?.join(rest),
+            None => key.into(),
+        },
+    };
+    fs::write(&buf_path, buf).map_err(Error::IoE)?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(["-Y", "sign", "-n", "git", "-f"]).arg(&key_path);
+    if key.starts_with("key::") {
+        cmd.arg("-U");
+    }
+    let status = cmd.arg(&buf_path).status().map_err(Error::IoE);
+
+    let sig = fs::read_to_string(&sig_path);
+    let _ = fs::remove_file(&buf_path);
+    let _ = fs::remove_file(&sig_path);
+    let _ = fs::remove_file(&literal_key_path);
+
+    match status? {
+        s if s.success() => sig.map_err(Error::IoE),
+        _ => Err(Error::Sign(format!("{program} failed to sign the data"))),
+    }
+}
@@ -0,0 +1,264 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, Model, editor,
+    git::{self, Hunk, Ident},
+    guard, llm,
+    template::{Task, Template},
+};
+
+pub const SPLIT_PMT: &str = "You are an assistant that splits code changes into logical Git commits.
+Given numbered hunks of a diff, group them into coherent commits. Every hunk should belong to exactly one commit.
+Order the commits so that each one makes sense on top of the previous ones.
+For each commit, write a single-line commit message in Conventional Commits format (e.g., \"feat:\", \"fix:\", \"docs:\", etc.).
+Output only JSON like [{\"message\": \"feat: add foo\", \"hunks\": [0, 2]}, {\"message\": \"fix: bar\", \"hunks\": [1]}].
+Do not include any extra text, code blocks, or formatting.
+{{hints}}{{user}}Hunks:
+{{hunks}}
+Output only the JSON plan for the hunks above.";
+
+const PLAN_HELP: &str = "# Edit the commit plan above. Each commit has a message and hunk numbers.
+# Hunks not in any commit are left in your working tree.
+# Lines starting with '#' will be ignored, and an empty plan aborts the split.";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Group {
+    pub message: String,
+    pub hunks: Vec<usize>,
+}
+
+/// `template` has other variables. (e.g. hints, branch)
+pub fn create_split_plan<T: AsRef<str>>(
+    hunks: &[Hunk],
+    template: Template,
+    model: Model,
+    api_key: Option<T>,
+) -> Result<Vec<Group>, Error> {
+    let source = hunks_to_string(hunks);
+    let conventional = template.is_default(Task::Split);
+    let (system, pmt) = template
+        .var("hunks", guard::fence("hunks", &source))
+        .render_parts();
+    let res = llm::call_llm(
+        system,
+        pmt,
+        model.provider,
+        model.model,
+        api_key.map(|f| f.as_ref().to_string()),
+        None,
+        None,
+    )
+    .map_err(Error::Llm)?;
+
+    parse_plan(res, hunks.len())?
+        .into_iter()
+        .map(|g| {
+            Ok(Group {
+                message: guard::check_cmt_msg(&g.message, &source, conventional)?,
+                hunks: g.hunks,
+            })
+        })
+        .collect()
+}
+
+fn hunks_to_string(hunks: &[Hunk]) -> String {
+    hunks
+        .iter()
+        .enumerate()
+        .map(|(i, h)| format!("--- hunk {i}: {} {}\n{}", h.path, h.header, h.patch))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// parse json plan. unknown or duplicated hunk numbers are dropped. (first group wins)
+pub fn parse_plan<T: AsRef<str>>(res: T, hunk_count: usize) -> Result<Vec<Group>, Error> {
+    let json = res
+        .as_ref()
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let plan = serde_json::from_str::<Vec<Group>>(json)
+        .map_err(|e| Error::InvalidSplitPlan(e.to_string()))?;
+
+    let mut used = vec![false; hunk_count];
+    let plan = plan
+        .into_iter()
+        .map(|g| Group {
+            message: g.message.trim().to_string(),
+            hunks: g
+                .hunks
+                .into_iter()
+                .filter(|&h| h < hunk_count && !std::mem::replace(&mut used[h], true))
+                .collect(),
+        })
+        .filter(|g| !g.hunks.is_empty() && !g.message.is_empty())
+        .collect::<Vec<_>>();
+
+    if plan.is_empty() {
+        Err(Error::InvalidSplitPlan("no commit in plan".to_string()))
+    } else {
+        Ok(plan)
+    }
+}
+
+pub fn plan_to_string(plan: &[Group], hunks: &[Hunk]) -> String {
+    let mut s = String::new();
+    for (i, g) in plan.iter().enumerate() {
+        s.push_str(&format!("{}. {}\n", i + 1, g.message));
+        for &h in &g.hunks {
+            s.push_str(&format!(
+                "    [{h}] {} {}\n",
+                hunks[h].path, hunks[h].header
+            ));
+        }
+    }
+    let left = left_hunks(plan, hunks.len());
+    if !left.is_empty() {
+        s.push_str(&format!("not committed: {left:?}\n"));
+    }
+    s
+}
+
+fn left_hunks(plan: &[Group], hunk_count: usize) -> Vec<usize> {
+    (0..hunk_count)
+        .filter(|h| !plan.iter().any(|g| g.hunks.contains(h)))
+        .collect()
+}
+
+/// edit plan as json in editor. hunk list is shown as comments.
+pub fn edit_plan<P: AsRef<Path>>(
+    project_path: P,
+    plan: &[Group],
+    hunks: &[Hunk],
+) -> Result<Vec<Group>, Error> {
+    let json =
+        serde_json::to_string_pretty(plan).map_err(|e| Error::InvalidSplitPlan(e.to_string()))?;
+    let list = hunks
+        .iter()
+        .enumerate()
+        .map(|(i, h)| format!("# [{i}] {} {}", h.path, h.header))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let edited = editor::edit_text(
+        project_path,
+        "GGW_SPLIT_PLAN",
+        format!("{json}\n\n{PLAN_HELP}\n#\n# Hunks:\n{list}\n"),
+    )?;
+    if edited.is_empty() {
+        return Err(Error::InvalidSplitPlan("empty plan".to_string()));
+    }
+    parse_plan(edited, hunks.len())
+}
+
+/// commit each group in order. changes staged before and not in the plan are staged again.
+/// on error, HEAD and index are restored to the state before split.
+pub fn apply_plan<P: AsRef<Path>>(
+    project_path: P,
+    plan: &[Group],
+    hunks: &[Hunk],
+    author: &Ident,
+    committer: &Ident,
+    verify: bool,
+) -> Result<(), Error> {
+    let backup = git::backup_index(&project_path)?;
+
+    let res = git::reset_index_to_head(&project_path).and_then(|_| {
+        plan.iter().try_for_each(|g| {
+            let picked = g.hunks.iter().map(|&h| &hunks[h]).collect::<Vec<_>>();
+            git::commit_hunks(
+                &project_path,
+                &picked,
+                &g.message,
+                author,
+                committer,
+                verify,
+            )?;
+            println!("committed: {}", g.message);
+            Ok(())
+        })
+    });
+
+    if let Err(e) = res {
+        git::restore_index(&project_path, &backup)?;
+        println!("split aborted. HEAD and index are restored.");
+        return Err(e);
+    }
+
+    let committed = plan
+        .iter()
+        .flat_map(|g| g.hunks.iter().map(|&h| &hunks[h]))
+        .collect::<Vec<_>>();
+    for p in git::restage(&project_path, &backup, &committed)? {
+        eprintln!("staged changes of {p} don't apply on the new commits. they are left unstaged.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use git2::Repository;
+
+    use super::{Group, apply_plan, left_hunks, parse_plan};
+    use crate::git::{self, Ident};
+
+    #[test]
+    fn test_parse_plan() {
+        let res = "```json\n[{\"message\": \"feat: a\", \"hunks\": [0, 2, 9]}, {\"message\": \"fix: b\", \"hunks\": [2]}, {\"message\": \"docs: c\", \"hunks\": [1]}]\n```";
+        let plan = parse_plan(res, 4).unwrap();
+        assert_eq!(
+            plan,
+            vec![
+                Group {
+                    message: "feat: a".to_string(),
+                    hunks: vec![0, 2]
+                },
+                Group {
+                    message: "docs: c".to_string(),
+                    hunks: vec![1]
+                },
+            ]
+        );
+        assert_eq!(left_hunks(&plan, 4), vec![3]);
+    }
+
+    #[test]
+    fn test_parse_plan_invalid() {
+        assert!(parse_plan("feat: a", 1).is_err());
+        assert!(parse_plan("[]", 1).is_err());
+    }
+
+    #[test]
+    fn test_apply_plan_keeps_staged() {
+        let dir = env::temp_dir().join(format!("ggw_split_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let ident = Ident::parse("a <a@example.com>").unwrap();
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+        git::git_commit(&dir, &"init", &ident, &ident, false).unwrap();
+
+        fs::write(dir.join("a.txt"), "b\n").unwrap();
+        fs::write(dir.join("b.txt"), "b\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path("b.txt".as_ref()).unwrap();
+        index.write().unwrap();
+
+        let hunks = git::get_hunks(&dir).unwrap();
+        let a = hunks.iter().position(|h| h.path == "a.txt").unwrap();
+        let plan = vec![Group {
+            message: "fix: a".to_string(),
+            hunks: vec![a],
+        }];
+        apply_plan(&dir, &plan, &hunks, &ident, &ident, false).unwrap();
+
+        assert_eq!(git::staged_paths(&dir).unwrap(), ["b.txt"]);
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("b.txt").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, conventional, git, storage::Storage};
+
+/// number of commit msgs used as examples when not set in config.
+pub const DEFAULT_SAMPLES: usize = 10;
+
+/// commit style learned from history. cached in `.git/ggw/style.json` until HEAD moves.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Style {
+    head: String,
+    samples: usize,
+    pub examples: Vec<String>,
+    pub scopes: Vec<String>,
+}
+
+impl<P: AsRef<Path>> Storage<P> for Style {}
+
+pub fn learn_style<P: AsRef<Path>>(project_path: P, samples: usize) -> Result<Style, Error> {
+    let cache_dir = git::git_dir(&project_path)?.join("ggw");
+    let cache_path = cache_dir.join("style.json");
+    let head = git::head_id(&project_path)?;
+
+    if let Ok(style) = Style::open::<Style>(&cache_path)
+        && style.head == head
+        && style.samples == samples
+    {
+        return Ok(style);
+    }
+
+    let examples = git::recent_summaries(&project_path, samples)?;
+    let mut scopes = examples
+        .iter()
+        .filter_map(|m| conventional::parse_header(m)?.scope.map(|s| s.to_string()))
+        .collect::<Vec<_>>();
+    scopes.sort();
+    scopes.dedup();
+
+    let style = Style {
+        head,
+        samples,
+        examples,
+        scopes,
+    };
+    fs::create_dir_all(&cache_dir).map_err(Error::IoE)?;
+    style.save(&cache_path, true).map_err(Error::StrE)?;
+    Ok(style)
+}
+
+/// few-shot examples for prompt. with `enforce`, scopes are limited to the used ones.
+pub fn style_hints(style: &Style, enforce: bool) -> Vec<String> {
+    let mut hints = Vec::new();
+    if !style.examples.is_empty() {
+        hints.push(format!(
+            "Follow the style (types, scopes, tense, emoji and language) of these recent commit messages in this repository:\n{}",
+            style
+                .examples
+                .iter()
+                .map(|e| format!("- {e}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    if enforce {
+        hints.push(if style.scopes.is_empty() {
+            "Do not use a scope. This repository doesn't use them.".to_string()
+        } else {
+            format!(
+                "Use a scope only from this list: {}. If none fits, omit the scope.",
+                style.scopes.join(", ")
+            )
+        });
+    }
+    hints
+}
+
+/// remove a scope which is not used in history.
+pub fn enforce_scope(msg: String, style: &Style) -> String {
+    match conventional::parse_header(&msg).and_then(|h| h.scope) {
+        Some(s) if !style.scopes.iter().any(|u| u == s) => {
+            println!("scope \"{s}\" is not used in this repository. removed.");
+            conventional::set_scope(&msg, None)
+        }
+        _ => msg,
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs;
+
+use clap::ValueEnum;
+
+use crate::{Error, cmt_msg, config::Config, doc, fixup, readme, split, sum};
+
+/// separates the system prompt and the user message in a template.
+pub const USER_MARKER: &str = "{{user}}";
+
+/// subcommands that have a prompt template.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Task {
+    Cmt,
+    Sum,
+    Rdm,
+    Split,
+    Fixup,
+    /// `rdm --section`
+    Section,
+    Contributing,
+    Architecture,
+    Module,
+    Rustdoc,
+}
+
+impl Task {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task::Cmt => "cmt",
+            Task::Sum => "sum",
+            Task::Rdm => "rdm",
+            Task::Split => "split",
+            Task::Fixup => "fixup",
+            Task::Section => "section",
+            Task::Contributing => "contributing",
+            Task::Architecture => "architecture",
+            Task::Module => "module",
+            Task::Rustdoc => "rustdoc",
+        }
+    }
+
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            Task::Cmt => cmt_msg::GEN_MSG_PMT,
+            Task::Sum => sum::DEFAULT_PROMT,
+            Task::Rdm => readme::DEFAULT_PROMT,
+            Task::Split => split::SPLIT_PMT,
+            Task::Fixup => fixup::FIXUP_PMT,
+            Task::Section => readme::SECTION_PMT,
+            Task::Contributing => doc::CONTRIBUTING_PMT,
+            Task::Architecture => doc::ARCHITECTURE_PMT,
+            Task::Module => doc::MODULE_PMT,
+            Task::Rustdoc => doc::RUSTDOC_PMT,
+        }
+    }
+}
+
+/// prompt text with `{{name}}` placeholders.
+/// unknown placeholders are left as is.
+#[derive(Debug, Clone)]
+pub struct Template {
+    text: String,
+    vars: Vec<(&'static str, String)>,
+}
+
+impl Template {
+    pub fn new(task: Task) -> Self {
+        Self::from_text(task.default_template())
+    }
+
+    /// template not bound to a task. e.g. preset of `cst`
+    pub fn from_text<T: AsRef<str>>(text: T) -> Self {
+        Self {
+            text: text.as_ref().to_string(),
+            vars: Vec::new(),
+        }
+    }
+
+    /// whether the text is the default of `task`, not replaced by config or `~/.ggw/prompts`.
+    pub fn is_default(&self, task: Task) -> bool {
+        self.text == task.default_template()
+    }
+
+    /// whether the text has `{{name}}`. to skip costly variables.
+    /// spaces in braces are allowed like `render_text`. e.g. `{{ diff }}`
+    pub fn uses(&self, name: &str) -> bool {
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            rest = &rest[start + 2..];
+            if rest
+                .find("}}")
+                .is_some_and(|end| rest[..end].trim() == name)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// `prompt.templates.<task>` in config > `~/.ggw/prompts/<task>.md` > default.
+    pub fn load(task: Task, config: &Config) -> Result<Self, Error> {
+        if let Some(text) = config.template(task.name()) {
+            return Ok(Self::from_text(text));
+        }
+        let path = home::home_dir()
+            .ok_or(Error::NotFoundHome)?
+            .join(".ggw")
+            .join("prompts")
+            .join(task.name())
+            .with_extension("md");
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::from_text(text)),
+            Err(_) => Ok(Self::new(task)),
+        }
+    }
+
+    /// set a variable. the last one wins.
+    pub fn var<T: AsRef<str>>(mut self, name: &'static str, value: T) -> Self {
+        self.vars.retain(|(n, _)| *n != name);
+        self.vars.push((name, value.as_ref().to_string()));
+        self
+    }
+
+    /// each hint is a line. `{{hints}}` is empty without hints.
+    pub fn hints(self, hints: &[String]) -> Self {
+        let hints = hints.iter().map(|h| format!("{h}\n")).collect::<String>();
+        self.var("hints", hints)
+    }
+
+    pub fn render(&self) -> String {
+        self.render_text(&self.text)
+    }
+
+    /// text before `{{user}}` is the system prompt, and after it is the user message.
+    /// without the marker, all is the user message.
+    pub fn render_parts(&self) -> (Option<String>, String) {
+        match self.text.split_once(USER_MARKER) {
+            Some((system, user)) => {
+                let system = self.render_text(system).trim_end().to_string();
+                (
+                    Some(system).filter(|s| !s.is_empty()),
+                    self.render_text(user),
+                )
+            }
+            None => (None, self.render()),
+        }
+    }
+
+    /// placeholders in values (e.g. a diff of a template) are not expanded.
+    fn render_text(&self, text: &str) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let found = after.find("}}").and_then(|end| {
+                let name = after[..end].trim();
+                let (_, value) = self.vars.iter().find(|(n, _)| *n == name)?;
+                Some((end, value))
+            });
+            match found {
+                Some((end, value)) => {
+                    out.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    out.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Task, Template};
+
+    #[test]
+    fn test_render() {
+        let t = Template::new(Task::Sum)
+            .hints(&["Respond in Japanese.".to_string()])
+            .var("diff", "+foo");
+        let (system, pmt) = t.render_parts();
+        assert!(system.unwrap().ends_with("Respond in Japanese."));
+        assert!(pmt.starts_with("--- diff here ---\n+foo\n"));
+        assert!(!pmt.contains("{{"));
+
+        let t = Template::from_text("{{branch}}: {{diff}} {{unknown}}")
+            .var("branch", "main")
+            .var("diff", "a")
+            .var("diff", "{{branch}}");
+        assert_eq!(t.render(), "main: {{branch}} {{unknown}}");
+
+        let t = Template::from_text("review {{ diff }} and {{stdin}}");
+        assert!(t.uses("diff") && t.uses("stdin"));
+        assert!(!t.uses("code"));
+        assert!(!t.is_default(Task::Cmt));
+        assert!(Template::new(Task::Cmt).is_default(Task::Cmt));
+        assert_eq!(t.var("diff", "a").render(), "review a and {{stdin}}");
+    }
+}
@@ -0,0 +1,55 @@
+/// append footer lines (e.g. `Co-authored-by: Name <email>`, `Closes #1`) to the last paragraph of msg.
+/// same line isn't added twice.
+pub fn append_trailers<T: AsRef<str>>(msg: T, trailers: &[String]) -> String {
+    let msg = msg.as_ref().trim_end();
+    let mut new: Vec<&str> = Vec::new();
+    for t in trailers.iter().map(|t| t.trim()) {
+        if !new.contains(&t) && !msg.lines().any(|l| l.trim() == t) {
+            new.push(t);
+        }
+    }
+    if new.is_empty() {
+        return msg.to_string();
+    }
+
+    let sep = if has_trailer_block(msg) { "\n" } else { "\n\n" };
+    format!("{msg}{sep}{}", new.join("\n"))
+}
+
+/// last paragraph is not the subject and all lines are footers.
+/// `Token: value` or `Token #value` like Conventional Commits.
+fn has_trailer_block(msg: &str) -> bool {
+    match msg.rsplit_once("\n\n") {
+        Some((_, last)) => last.lines().all(|l| {
+            l.split_once(": ")
+                .or_else(|| l.split_once(" #"))
+                .is_some_and(|(k, _)| !k.is_empty() && !k.contains(' '))
+        }),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::append_trailers;
+
+    #[test]
+    fn test_append_trailers() {
+        let co = ["Co-authored-by: Foo <foo@example.com>".to_string()];
+        assert_eq!(
+            append_trailers("feat: add foo", &co),
+            "feat: add foo\n\nCo-authored-by: Foo <foo@example.com>"
+        );
+        assert_eq!(
+            append_trailers("feat: add foo\n\nCloses #1\n", &co),
+            "feat: add foo\n\nCloses #1\nCo-authored-by: Foo <foo@example.com>"
+        );
+        assert_eq!(
+            append_trailers(
+                "feat: add foo\n\nCo-authored-by: Foo <foo@example.com>",
+                &co
+            ),
+            "feat: add foo\n\nCo-authored-by: Foo <foo@example.com>"
+        );
+    }
+}